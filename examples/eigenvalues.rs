@@ -0,0 +1,34 @@
+use rust_machine_learning::prelude::*;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+fn main() {
+    let n = 5;
+    let m = 5;
+
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+    
+    let mut a = Matrix::zeros(n, m);
+
+    for i in 0..n {
+        for j in 0..m {
+            a[(i, j)] = normal.sample(&mut rng);
+        }
+    }
+
+    let b = a.mul_tr(&a);
+    let eig = eigenvalues(&b).unwrap();
+    let vecs = eigenvectors(&b).unwrap();
+
+    println!("{:?}", b);
+    println!();
+    println!("{:?}", eig);
+    println!();
+    println!("{:?}", vecs.0);
+    println!();
+    println!("{:?}", vecs.1);
+}
@@ -0,0 +1,33 @@
+use rust_machine_learning::prelude::*;
+use rand::Rng;
+use rand_distr::StandardNormal;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[allow(clippy::needless_range_loop)]
+fn main() {
+    let n = 1000;
+    let m = 3000;
+
+    let mut lr = LinearRegression::new(&m, &1000, &1024, &0.0, &0.001, &0.005);
+    let mut data:Vec<f64> = vec![0.0;n*m];
+    let mut labels:Vec<f64> = vec![0.0;n];
+
+    for i in 0..n {
+        for j in 0..m {
+            data[i*m + j] = rand::thread_rng().sample(StandardNormal);
+        }
+        labels[i] = rand::thread_rng().sample(StandardNormal);
+    }
+
+    lr.train(&data, &labels, n, m);
+
+    let mut query:Vec<f64> = vec![0.0;m];
+    for j in 0..m {
+        query[j] = rand::thread_rng().sample(StandardNormal);
+    }
+
+    let preds = lr.predict(&data);
+    println!("{:?}", preds);
+}
@@ -0,0 +1,40 @@
+use rust_machine_learning::prelude::*;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+fn main() {
+    let n = 1234;
+    let m = 569;
+
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+    
+    let mut a = Matrix::zeros(n, m);
+
+    for i in 0..n {
+        for j in 0..m {
+            a[(i, j)] = normal.sample(&mut rng);
+        }
+    }
+
+    let lu = lu_decomposition(&a).unwrap();
+    
+    let eye = lu.0;
+    let l = lu.1;
+    let u = lu.2;
+
+    let x = &eye * &a;
+    let y = &l * &u;
+
+    for i in 0..n {
+        for j in 0..m {
+            if (x[(i, j)]-y[(i, j)]).abs()/x[(i, j)].abs() > 0.001  {
+                println!("{:?}, {:?}, {:?}, {:?}", i, j, x[(i, j)], y[(i, j)]);
+            }
+        }
+    }
+
+}
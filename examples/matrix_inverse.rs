@@ -0,0 +1,26 @@
+use rust_machine_learning::prelude::*;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+fn main() {
+    let n = 500;
+
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+
+    let mut a = Matrix::zeros(n, n);
+
+    for i in 0..n {
+        for j in 0..n {
+            a[(i, j)] = normal.sample(&mut rng);
+        }
+    }
+
+    let sol = inverse(&a).unwrap();
+
+    println!("{:?}", a);
+    println!("{:?}", sol);
+}
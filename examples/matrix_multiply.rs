@@ -0,0 +1,42 @@
+use rust_machine_learning::matrix_utils::*;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
+use std::time::SystemTime;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[allow(clippy::needless_range_loop)]
+fn main() {
+    // Matrix multiplication
+    let n:usize = 101;
+    let m:usize = 511;
+    let p:usize = 397;
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+    let mut inp1:Vec<f64> = vec![0.0;n*m];
+    let mut inp2:Vec<f64> = vec![0.0;m*p];
+
+    for i in 0..n*m {
+        inp1[i] = normal.sample(&mut rng);
+    }
+
+    for i in 0..m*p {
+        inp2[i] = normal.sample(&mut rng);
+    }
+
+    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
+    let prod1 = matrix_multiply_simd(&inp1, &inp2, n, m, p).unwrap();
+    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
+
+    println!("{:?}", end_time-start_time);
+
+    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
+    let prod2 = matrix_multiply(&inp1, &inp2, n, m, p).unwrap();
+    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
+
+    println!("{:?}", end_time-start_time);
+
+    assert!(prod1 == prod2, "Matrix multiplications results are different");
+
+}
@@ -0,0 +1,27 @@
+use rust_machine_learning::row_echelon::row_echelon;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[allow(clippy::needless_range_loop)]
+fn main() {
+    let n = 10;
+    let m = 5;
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+    let mut mat:Vec<f64> = vec![0.0;n*m];
+    for i in 0..n*m {
+        mat[i] = normal.sample(&mut rng);
+    }
+
+    row_echelon(&mut mat, n, m);
+    
+    for i in 0..n {
+        for j in 0..m {
+            print!("{:?} ", mat[i*m+j]);
+        }
+        println!();
+    }
+}
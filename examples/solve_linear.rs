@@ -0,0 +1,35 @@
+use rust_machine_learning::prelude::*;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[allow(clippy::needless_range_loop)]
+fn main() {
+    let n = 145;
+
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+
+    let mut a = Matrix::zeros(n, n);
+    let mut b:Vec<f64> = vec![0.0;n];
+
+    for i in 0..n {
+        for j in 0..n {
+            a[(i, j)] = normal.sample(&mut rng);
+        }
+    }
+
+    for i in 0..n {
+        b[i] = normal.sample(&mut rng);
+    }
+
+    let (x, diagnostics) = solve_with_diagnostics(&a, &b).unwrap();
+
+    println!("{:?}", &x[0..5]);
+    println!("{:?}", diagnostics);
+
+    let (_, report) = solve_refined(&a, &b, &RefineOptions::new(10, Accumulation::Extended)).unwrap();
+    println!("{:?}", report);
+}
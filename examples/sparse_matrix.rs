@@ -0,0 +1,74 @@
+use rust_machine_learning::sparse_matrix::*;
+use rand_distr::{Distribution, Normal, Uniform};
+use rand::thread_rng;
+use std::time::SystemTime;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+fn main() {
+    let n = 500;
+    let m = 500;
+    let k = 0; //4*n*m/5;
+
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+    
+    let mut a:Vec<f64> = vec![0.0;n*m];
+    let mut b:Vec<f64> = vec![0.0;n*m];
+
+    for i in 0..n*m {
+        a[i] = normal.sample(&mut rng);
+        b[i] = normal.sample(&mut rng);
+    }
+
+    let uniform = Uniform::new(0, n*m);
+
+    for _ in 0..k {
+        let j = uniform.sample(&mut rng);
+        a[j] = 0.0;
+    }
+
+    for _ in 0..k {
+        let j = uniform.sample(&mut rng);
+        b[j] = 0.0;
+    }
+
+    let c = SparseMatrix::create(n, m, &a);
+    let d = SparseMatrix::create(m, n, &b);
+
+    // let e = get_sub_mat(&c, 1, 3, 2, 4);
+    // let f = convert_to_array(&e);
+
+    // let g = add(&c, &d);
+    // let h = convert_to_array(&g);
+
+    // let j = mul(&c, &d);
+    // let k = convert_to_array(&j);
+
+    // let c1 = Arc::new(c);
+    // let d1 = Arc::new(d);
+
+    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
+    let _l = dot(&c, &d).unwrap();
+    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
+    println!("{:?}", end_time-start_time);
+    // let m = convert_to_array(&l);
+
+    // let q = transpose(&c);
+    // let y = convert_to_array(&q);
+
+    // println!("{:?}", a);
+    // println!();
+    // println!("{:?}", b);
+    // println!();
+    // println!("{:?}", f);
+    // println!();
+    // println!("{:?}", h);
+    // println!();
+    // println!("{:?}", k);
+    // println!();
+    // println!("{:?}", m);
+    // println!();
+    // println!("{:?}", y);
+}
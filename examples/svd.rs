@@ -0,0 +1,41 @@
+use rust_machine_learning::prelude::*;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
+use std::time::SystemTime;
+
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+fn main() {
+    let n = 500;
+    let m = 500;
+
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+    
+    let mut a = Matrix::zeros(n, m);
+
+    for i in 0..n {
+        for j in 0..m {
+            a[(i, j)] = normal.sample(&mut rng);
+        }
+    }
+
+    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
+    let b = golub_reisch_svd(&a).unwrap();
+    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
+    println!("{:?}", end_time-start_time);
+    let c = &(&b.0 * &b.1) * &b.2;
+
+    // println!("{:?}", a);
+    // println!();
+    // println!("{:?}", b.1);
+    // println!();
+    // println!("{:?}", c);
+
+    for i in 0..n {
+        for j in 0..m {
+            assert!((c[(i, j)]-a[(i, j)]).abs() < 1e-5, "Some issue in SVD !!! {}, {}", a[(i, j)], c[(i, j)]);
+        }
+    }
+}
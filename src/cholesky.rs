@@ -0,0 +1,405 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::solve_linear::{forward_sub, backward_sub};
+use crate::row_echelon::{swap_rows, swap_cols};
+use rayon::prelude::*;
+use std::cmp::min;
+
+// Width of the diagonal blocks; the trailing update of each block step goes through GEMM.
+const CHOLESKY_BLOCK:usize = 64;
+
+// A = LL' for symmetric positive definite A. Only the lower triangle of A is read.
+#[derive(Clone, Debug)]
+pub struct Cholesky {
+    l: Matrix,
+    lt: Matrix,
+}
+
+// Unblocked factorization of the diagonal block starting at k, assuming the columns left of k
+// have already been subtracted out.
+fn factor_diagonal_block(a:&mut Matrix, k:usize, kb:usize) -> Result<(), LinalgError> {
+    for j in k..k+kb {
+        let d = a[(j, j)]-dot_product_simd(&a.row(j)[k..j], &a.row(j)[k..j]);
+
+        if d <= 0.0 || !d.is_finite() {
+            return Err(LinalgError::NotPositiveDefinite { index: j });
+        }
+
+        let d = d.sqrt();
+        a[(j, j)] = d;
+
+        for i in j+1..k+kb {
+            let p = dot_product_simd(&a.row(i)[k..j], &a.row(j)[k..j]);
+            a[(i, j)] = (a[(i, j)]-p)/d;
+        }
+    }
+    return Ok(());
+}
+
+pub fn cholesky(a:&Matrix) -> Result<Matrix, LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
+
+    let n = a.nrow;
+    let mut l = a.clone();
+
+    for k in (0..n).step_by(CHOLESKY_BLOCK) {
+        let kb = min(CHOLESKY_BLOCK, n-k);
+        factor_diagonal_block(&mut l, k, kb)?;
+
+        if k+kb == n {
+            break;
+        }
+
+        // L21 = A21 L11^-T, one independent forward substitution per row.
+        let (top, bottom) = l.as_mut_slice().split_at_mut((k+kb)*n);
+        bottom.par_chunks_mut(n).for_each(|row| {
+            for j in k..k+kb {
+                let p = dot_product_simd(&row[k..j], &top[j*n+k..j*n+j]);
+                row[j] = (row[j]-p)/top[j*n+j];
+            }
+        });
+
+        // A22 -= L21 L21', lower triangle only.
+        let l21 = l.view().subview(k+kb..n, k..k+kb);
+        let w = matrix_multiply_view(l21, l21.t()).unwrap();
+
+        for i in k+kb..n {
+            let row = &mut l.row_mut(i)[k+kb..i+1];
+            let wi = &w.row(i-k-kb)[0..i-k-kb+1];
+            for j in 0..row.len() {
+                row[j] -= wi[j];
+            }
+        }
+    }
+
+    for i in 0..n {
+        for j in i+1..n {
+            l[(i, j)] = 0.0;
+        }
+    }
+
+    return Ok(l);
+}
+
+impl Cholesky {
+    pub fn new(a:&Matrix) -> Result<Self, LinalgError> {
+        let l = cholesky(a)?;
+        let lt = l.transpose();
+        return Ok(Self {
+            l,
+            lt,
+        });
+    }
+
+    pub fn l(&self) -> &Matrix {
+        return &self.l;
+    }
+
+    pub fn solve(&self, b:&[f64]) -> Result<Vec<f64>, LinalgError> {
+        check_finite(b)?;
+        let y = forward_sub(&self.l, b)?;
+        return backward_sub(&self.lt, &y);
+    }
+
+    pub fn solve_matrix(&self, b:&Matrix) -> Result<Matrix, LinalgError> {
+        let n = self.l.nrow;
+        if b.nrow != n {
+            return Err(LinalgError::DimensionMismatch { expected: (n, b.ncol), found: b.shape() });
+        }
+
+        let mut x = Matrix::zeros(n, b.ncol);
+        for j in 0..b.ncol {
+            let xj = self.solve(&b.column(j))?;
+            for i in 0..n {
+                x[(i, j)] = xj[i];
+            }
+        }
+        return Ok(x);
+    }
+
+    // log det A = 2 sum log l_ii, finite even when det A over- or underflows.
+    pub fn log_det(&self) -> f64 {
+        let mut out = 0.0;
+        for i in 0..self.l.nrow {
+            out += self.l[(i, i)].ln();
+        }
+        return 2.0*out;
+    }
+
+    pub fn det(&self) -> f64 {
+        let mut out = 1.0;
+        for i in 0..self.l.nrow {
+            out *= self.l[(i, i)];
+        }
+        return out*out;
+    }
+
+    pub fn inverse(&self) -> Result<Matrix, LinalgError> {
+        return self.solve_matrix(&Matrix::identity(self.l.nrow));
+    }
+}
+
+// P'AP = LDL' with symmetric diagonal pivoting for positive semi-definite A. L is unit lower
+// triangular and the pivots d are non-increasing; once the largest remaining pivot is at or
+// below tol the factorization stops, so rank() is the numerical rank of A. Indefinite input is
+// rejected with NotPositiveDefinite rather than truncated.
+#[derive(Clone, Debug)]
+pub struct Ldlt {
+    l: Matrix,
+    lt: Matrix,
+    d: Vec<f64>,
+    perm: Vec<usize>,
+    tol: f64,
+    rank: usize,
+}
+
+impl Ldlt {
+    pub fn new(a:&Matrix) -> Result<Self, LinalgError> {
+        return Ldlt::with_tol(a, None);
+    }
+
+    // When tol is None it defaults to n*eps*max|a_ii|.
+    pub fn with_tol(a:&Matrix, tol:Option<f64>) -> Result<Self, LinalgError> {
+        check_square(a.nrow, a.ncol)?;
+        check_finite(a.as_slice())?;
+
+        let n = a.nrow;
+        let mut w = a.clone();
+        let mut perm:Vec<usize> = (0..n).collect();
+        let mut d:Vec<f64> = vec![0.0;n];
+        let mut rank = 0;
+
+        let tol = match tol {
+            Some(t) => t.max(0.0),
+            None => {
+                let dmax = (0..n).fold(0.0, |acc:f64, i| acc.max(a[(i, i)].abs()));
+                (n as f64)*f64::EPSILON*dmax
+            }
+        };
+
+        for j in 0..n {
+            let mut p = j;
+            for i in j+1..n {
+                if w[(i, i)] > w[(p, p)] {
+                    p = i;
+                }
+            }
+
+            if p != j {
+                swap_rows(w.as_mut_slice(), n, j, p);
+                swap_cols(w.as_mut_slice(), n, n, j, p);
+                perm.swap(j, p);
+            }
+
+            let pivot = w[(j, j)];
+
+            if pivot < -tol {
+                return Err(LinalgError::NotPositiveDefinite { index: j });
+            }
+
+            // Every remaining diagonal is negligible, so a PSD trailing block must be negligible
+            // as a whole; a negative diagonal or a sizeable off-diagonal means A is indefinite.
+            if pivot <= tol {
+                for i in j..n {
+                    for k in j..n {
+                        if (i == k && w[(i, k)] < -tol) || (i != k && w[(i, k)].abs() > tol) {
+                            return Err(LinalgError::NotPositiveDefinite { index: j });
+                        }
+                    }
+                }
+                for i in j..n {
+                    for k in j..n {
+                        w[(i, k)] = 0.0;
+                    }
+                }
+                break;
+            }
+
+            d[j] = pivot;
+            rank += 1;
+
+            for i in j+1..n {
+                w[(i, j)] /= pivot;
+            }
+
+            // Trailing Schur complement, kept symmetric so later pivot swaps stay valid.
+            let col:Vec<f64> = (j+1..n).map(|i| w[(i, j)]).collect();
+            let (_, bottom) = w.as_mut_slice().split_at_mut((j+1)*n);
+            bottom.par_chunks_mut(n).enumerate().for_each(|(r, row)| {
+                mul_sub_const(&col, &mut row[j+1..n], col[r]*pivot, n-j-1);
+            });
+        }
+
+        let mut l = Matrix::identity(n);
+        for i in 0..n {
+            for j in 0..min(i, rank) {
+                l[(i, j)] = w[(i, j)];
+            }
+        }
+        let lt = l.transpose();
+
+        return Ok(Self {
+            l,
+            lt,
+            d,
+            perm,
+            tol,
+            rank,
+        });
+    }
+
+    pub fn l(&self) -> &Matrix {
+        return &self.l;
+    }
+
+    pub fn d(&self) -> &[f64] {
+        return &self.d;
+    }
+
+    // Row i of P'AP is row perm[i] of A.
+    pub fn pivots(&self) -> &[usize] {
+        return &self.perm;
+    }
+
+    pub fn rank(&self) -> usize {
+        return self.rank;
+    }
+
+    pub fn tol(&self) -> f64 {
+        return self.tol;
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn solve(&self, b:&[f64]) -> Result<Vec<f64>, LinalgError> {
+        let n = self.l.nrow;
+        if b.len() != n {
+            return Err(LinalgError::DimensionMismatch { expected: (n, 1), found: (b.len(), 1) });
+        }
+        check_finite(b)?;
+
+        if self.rank < n {
+            return Err(LinalgError::Singular { index: self.rank });
+        }
+
+        let pb:Vec<f64> = self.perm.iter().map(|&i| b[i]).collect();
+        let mut y = forward_sub(&self.l, &pb)?;
+        for i in 0..n {
+            y[i] /= self.d[i];
+        }
+        let z = backward_sub(&self.lt, &y)?;
+
+        let mut x = vec![0.0;n];
+        for i in 0..n {
+            x[self.perm[i]] = z[i];
+        }
+        return Ok(x);
+    }
+
+    // log det A; negative infinity when A is singular.
+    pub fn log_det(&self) -> f64 {
+        if self.rank < self.d.len() {
+            return f64::NEG_INFINITY;
+        }
+        return self.d.iter().map(|x| x.ln()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    // B'B + n*I, comfortably positive definite.
+    fn spd(n:usize, seed:u64) -> Matrix {
+        let b = random(n, n, seed);
+        let mut a = b.tr_mul(&b);
+        for i in 0..n {
+            a[(i, i)] += n as f64;
+        }
+        return a;
+    }
+
+    fn permuted(a:&Matrix, perm:&[usize]) -> Matrix {
+        let n = a.nrow;
+        let mut out = Matrix::zeros(n, n);
+        for i in 0..n {
+            for j in 0..n {
+                out[(i, j)] = a[(perm[i], perm[j])];
+            }
+        }
+        return out;
+    }
+
+    #[test]
+    fn blocked_cholesky_reconstructs_and_solves() {
+        // Larger than one block so the GEMM trailing update runs.
+        let n = 150;
+        let a = spd(n, 1);
+        let chol = Cholesky::new(&a).unwrap();
+        assert!(max_abs_diff(&chol.l().mul_tr(chol.l()), &a) < 1e-9);
+
+        let b = random(n, 1, 2).into_vec();
+        let x = chol.solve(&b).unwrap();
+        let r = a.clone()*Matrix::column_vector(&x);
+        for i in 0..n {
+            assert!((r[(i, 0)]-b[i]).abs() < 1e-9);
+        }
+        assert!(max_abs_diff(&(a*chol.inverse().unwrap()), &Matrix::identity(n)) < 1e-9);
+    }
+
+    #[test]
+    fn cholesky_rejects_indefinite() {
+        let a = Matrix::new(2, 2, vec![1.0, 2.0, 2.0, 1.0]);
+        assert!(matches!(Cholesky::new(&a), Err(LinalgError::NotPositiveDefinite { index: 1 })));
+    }
+
+    #[test]
+    fn ldlt_factors_spd() {
+        let a = spd(20, 3);
+        let ldlt = Ldlt::new(&a).unwrap();
+        assert_eq!(ldlt.rank(), 20);
+        let ld = ldlt.l().clone()*Matrix::from_diag(ldlt.d());
+        assert!(max_abs_diff(&ld.mul_tr(ldlt.l()), &permuted(&a, ldlt.pivots())) < 1e-10);
+        assert!(ldlt.d().windows(2).all(|w| w[0] >= w[1]));
+        let chol = Cholesky::new(&a).unwrap();
+        assert!((ldlt.log_det()-chol.log_det()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ldlt_reports_rank_of_semidefinite() {
+        let b = random(3, 8, 4);
+        let a = b.tr_mul(&b);
+        let ldlt = Ldlt::new(&a).unwrap();
+        assert_eq!(ldlt.rank(), 3);
+        let ld = ldlt.l().clone()*Matrix::from_diag(ldlt.d());
+        assert!(max_abs_diff(&ld.mul_tr(ldlt.l()), &permuted(&a, ldlt.pivots())) < 1e-10);
+        assert!(matches!(ldlt.solve(&[1.0;8]), Err(LinalgError::Singular { index: 3 })));
+        assert_eq!(ldlt.log_det(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn ldlt_rejects_indefinite() {
+        let cases = [
+            Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]),
+            Matrix::from_diag(&[0.0, -1.0]),
+            Matrix::from_diag(&[2.0, 1.0, -3.0]),
+            Matrix::new(3, 3, vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0]),
+        ];
+        for a in cases.iter() {
+            assert!(matches!(Ldlt::new(a), Err(LinalgError::NotPositiveDefinite { .. })), "{:?}", a);
+        }
+    }
+}
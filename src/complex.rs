@@ -0,0 +1,216 @@
+#![allow(clippy::needless_return)]
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Div, Neg, AddAssign, SubAssign, MulAssign};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re:f64, im:f64) -> Self {
+        Self {
+            re,
+            im,
+        }
+    }
+
+    pub fn from_real(re:f64) -> Self {
+        return Complex::new(re, 0.0);
+    }
+
+    pub fn i() -> Self {
+        return Complex::new(0.0, 1.0);
+    }
+
+    pub fn conj(&self) -> Self {
+        return Complex::new(self.re, -self.im);
+    }
+
+    pub fn abs(&self) -> f64 {
+        return self.re.hypot(self.im);
+    }
+
+    pub fn norm_sqr(&self) -> f64 {
+        return self.re*self.re+self.im*self.im;
+    }
+
+    pub fn arg(&self) -> f64 {
+        return self.im.atan2(self.re);
+    }
+
+    pub fn is_real(&self) -> bool {
+        return self.im == 0.0;
+    }
+
+    pub fn is_finite(&self) -> bool {
+        return self.re.is_finite() && self.im.is_finite();
+    }
+
+    pub fn from_polar(r:f64, theta:f64) -> Self {
+        return Complex::new(r*theta.cos(), r*theta.sin());
+    }
+
+    // Principal square root, with the branch cut on the negative real axis.
+    pub fn sqrt(&self) -> Self {
+        if self.im == 0.0 {
+            if self.re >= 0.0 {
+                return Complex::from_real(self.re.sqrt());
+            }
+            return Complex::new(0.0, (-self.re).sqrt());
+        }
+        let r = self.abs();
+        let re = ((r+self.re)/2.0).sqrt();
+        let im = ((r-self.re)/2.0).sqrt();
+        return Complex::new(re, if self.im < 0.0 { -im } else { im });
+    }
+
+    pub fn exp(&self) -> Self {
+        return Complex::from_polar(self.re.exp(), self.im);
+    }
+
+    // Principal logarithm.
+    pub fn ln(&self) -> Self {
+        return Complex::new(self.abs().ln(), self.arg());
+    }
+
+    pub fn powf(&self, p:f64) -> Self {
+        if self.re == 0.0 && self.im == 0.0 {
+            return if p == 0.0 { Complex::from_real(1.0) } else { Complex::default() };
+        }
+        return Complex::from_polar(self.abs().powf(p), self.arg()*p);
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re:f64) -> Self {
+        return Complex::from_real(re);
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        if self.im < 0.0 {
+            write!(f, "{}-{}i", self.re, -self.im)
+        }
+        else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs:Complex) -> Complex {
+        return Complex::new(self.re+rhs.re, self.im+rhs.im);
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs:Complex) -> Complex {
+        return Complex::new(self.re-rhs.re, self.im-rhs.im);
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs:Complex) -> Complex {
+        return Complex::new(self.re*rhs.re-self.im*rhs.im, self.re*rhs.im+self.im*rhs.re);
+    }
+}
+
+// Smith's algorithm, which avoids overflow in |rhs|^2.
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs:Complex) -> Complex {
+        if rhs.re.abs() >= rhs.im.abs() {
+            let r = rhs.im/rhs.re;
+            let d = rhs.re+rhs.im*r;
+            return Complex::new((self.re+self.im*r)/d, (self.im-self.re*r)/d);
+        }
+        let r = rhs.re/rhs.im;
+        let d = rhs.re*r+rhs.im;
+        return Complex::new((self.re*r+self.im)/d, (self.im*r-self.re)/d);
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        return Complex::new(-self.re, -self.im);
+    }
+}
+
+impl Add<f64> for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs:f64) -> Complex {
+        return Complex::new(self.re+rhs, self.im);
+    }
+}
+
+impl Sub<f64> for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs:f64) -> Complex {
+        return Complex::new(self.re-rhs, self.im);
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs:f64) -> Complex {
+        return Complex::new(self.re*rhs, self.im*rhs);
+    }
+}
+
+impl Div<f64> for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs:f64) -> Complex {
+        return Complex::new(self.re/rhs, self.im/rhs);
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, rhs:Complex) {
+        *self = *self+rhs;
+    }
+}
+
+impl SubAssign for Complex {
+    fn sub_assign(&mut self, rhs:Complex) {
+        *self = *self-rhs;
+    }
+}
+
+impl MulAssign for Complex {
+    fn mul_assign(&mut self, rhs:Complex) {
+        *self = *self*rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_and_elementary_functions() {
+        let z = Complex::new(3.0, 4.0);
+        assert_eq!(z.abs(), 5.0);
+        assert_eq!(z*z.conj(), Complex::from_real(25.0));
+        assert!((z/z-Complex::from_real(1.0)).abs() < 1e-15);
+        assert!((z.sqrt()-Complex::new(2.0, 1.0)).abs() < 1e-15);
+        assert!((z.ln().exp()-z).abs() < 1e-14);
+        assert!((Complex::i()*Complex::i()+1.0).abs() < 1e-15);
+        assert!((Complex::from_real(-4.0).sqrt()-Complex::new(0.0, 2.0)).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,104 @@
+#![allow(clippy::needless_return)]
+use crate::linalg_error::LinalgError;
+
+// Stopping rule shared by the iterative routines. None picks the routine's own default, usually
+// a cap proportional to the problem size and a relative tolerance of a small multiple of eps.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct IterOptions {
+    pub max_iter: Option<usize>,
+    pub tol: Option<f64>,
+}
+
+impl IterOptions {
+    pub fn new(max_iter:Option<usize>, tol:Option<f64>) -> Self {
+        Self {
+            max_iter,
+            tol,
+        }
+    }
+
+    pub fn max_iter_or(&self, default:usize) -> usize {
+        return self.max_iter.unwrap_or(default);
+    }
+
+    pub fn tol_or(&self, default:f64) -> f64 {
+        return self.tol.map_or(default, |t| t.max(0.0));
+    }
+}
+
+// Outcome of an iterative routine. residual is the largest off-diagonal entry that was
+// neglected, or that is still left when the routine ran out of iterations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Convergence {
+    pub iterations: usize,
+    pub residual: f64,
+    pub converged: bool,
+}
+
+impl Convergence {
+    // Turns a run that hit max_iter into NotConverged, for callers that need the full result.
+    pub fn check(&self) -> Result<(), LinalgError> {
+        if !self.converged {
+            return Err(LinalgError::NotConverged { iterations: self.iterations });
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dense_matrix::Matrix;
+    use crate::symmetric_eigen::symmetric_eigenvalues_with_options;
+    use crate::general_eigen::real_schur_with_options;
+    use crate::svd::golub_reisch_svd_with_options;
+
+    fn test_matrix(n:usize) -> Matrix {
+        return Matrix::new(n, n, (0..n*n).map(|k| ((k*37+11)%17) as f64-8.0).collect());
+    }
+
+    #[test]
+    fn default_options_converge() {
+        let a = test_matrix(8);
+        let s = a.clone()+a.transpose();
+        let defaults = IterOptions::default();
+
+        let (_, conv) = symmetric_eigenvalues_with_options(&s, &defaults).unwrap();
+        assert!(conv.converged && conv.iterations > 0);
+        assert!(conv.residual <= f64::EPSILON*s.norm_inf());
+        assert_eq!(conv.check(), Ok(()));
+
+        let (_, _, conv) = real_schur_with_options(&a, &defaults).unwrap();
+        assert!(conv.converged && conv.iterations > 0);
+
+        let (_, _, _, conv) = golub_reisch_svd_with_options(&a, &defaults).unwrap();
+        assert!(conv.converged && conv.iterations > 0);
+    }
+
+    #[test]
+    fn exhausted_iterations_are_reported() {
+        let a = test_matrix(8);
+        let s = a.clone()+a.transpose();
+        let none = IterOptions::new(Some(0), None);
+
+        let (_, conv) = symmetric_eigenvalues_with_options(&s, &none).unwrap();
+        assert!(!conv.converged && conv.residual > 0.0);
+        assert_eq!(conv.check(), Err(LinalgError::NotConverged { iterations: 0 }));
+
+        let (_, _, conv) = real_schur_with_options(&a, &none).unwrap();
+        assert!(!conv.converged && conv.residual > 0.0);
+
+        let (_, _, _, conv) = golub_reisch_svd_with_options(&a, &none).unwrap();
+        assert!(!conv.converged && conv.residual > 0.0);
+    }
+
+    #[test]
+    fn loose_tolerance_takes_fewer_iterations() {
+        let a = test_matrix(10);
+        let (_, _, _, tight) = golub_reisch_svd_with_options(&a, &IterOptions::default()).unwrap();
+        let (_, _, _, loose) = golub_reisch_svd_with_options(&a, &IterOptions::new(None, Some(1e-3))).unwrap();
+        assert!(loose.converged);
+        assert!(loose.iterations <= tight.iterations);
+        assert_eq!(IterOptions::new(None, Some(-1.0)).tol_or(0.5), 0.0);
+    }
+}
@@ -0,0 +1,249 @@
+#![allow(dead_code, clippy::needless_return)]
+use std::ops::{Add, Sub, Mul, Index, IndexMut};
+use crate::matrix_utils::*;
+use crate::gemm::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub nrow: usize,
+    pub ncol: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn new(
+        nrow: usize,
+        ncol: usize,
+        data: Vec<f64>,
+    ) -> Self {
+
+        assert!(data.len() == nrow*ncol, "Matrix data has length {} but shape is {}x{}", data.len(), nrow, ncol);
+
+        Self {
+            nrow,
+            ncol,
+            data,
+        }
+    }
+}
+
+impl Matrix {
+    pub fn zeros(nrow:usize, ncol:usize) -> Self {
+        return Matrix::new(nrow, ncol, vec![0.0;nrow*ncol]);
+    }
+
+    pub fn identity(n:usize) -> Self {
+        return Matrix::new(n, n, identity(n));
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn from_diag(d:&[f64]) -> Self {
+        let n = d.len();
+        let mut a = Matrix::zeros(n, n);
+        for i in 0..n {
+            a.data[i*(n+1)] = d[i];
+        }
+        return a;
+    }
+
+    pub fn column_vector(v:&[f64]) -> Self {
+        return Matrix::new(v.len(), 1, v.to_vec());
+    }
+}
+
+impl Matrix {
+    pub fn shape(&self) -> (usize, usize) {
+        return (self.nrow, self.ncol);
+    }
+
+    pub fn is_square(&self) -> bool {
+        return self.nrow == self.ncol;
+    }
+
+    pub fn as_slice(&self) -> &[f64] {
+        return &self.data;
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [f64] {
+        return &mut self.data;
+    }
+
+    pub fn into_vec(self) -> Vec<f64> {
+        return self.data;
+    }
+
+    pub fn row(&self, i:usize) -> &[f64] {
+        return &self.data[i*self.ncol..(i+1)*self.ncol];
+    }
+
+    pub fn row_mut(&mut self, i:usize) -> &mut [f64] {
+        return &mut self.data[i*self.ncol..(i+1)*self.ncol];
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn column(&self, j:usize) -> Vec<f64> {
+        let mut out = vec![0.0;self.nrow];
+        for i in 0..self.nrow {
+            out[i] = self.data[i*self.ncol+j];
+        }
+        return out;
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn diag(&self) -> Vec<f64> {
+        let r = std::cmp::min(self.nrow, self.ncol);
+        let mut out = vec![0.0;r];
+        for i in 0..r {
+            out[i] = self.data[i*(self.ncol+1)];
+        }
+        return out;
+    }
+}
+
+impl Matrix {
+    pub fn transpose(&self) -> Matrix {
+        return Matrix::new(self.ncol, self.nrow, transpose(&self.data, self.nrow, self.ncol));
+    }
+
+    pub fn sub_mat(&self, r_start:usize, r_end:usize, c_start:usize, c_end:usize) -> Matrix {
+        let b = sub_mat(&self.data, self.nrow, self.ncol, r_start, r_end, c_start, c_end);
+        return Matrix::new(r_end-r_start+1, c_end-c_start+1, b);
+    }
+
+    pub fn copy_sub_mat(&mut self, b:&Matrix, r_start:usize, c_start:usize) {
+        copy_sub_mat(&mut self.data, &b.data, self.nrow, self.ncol, r_start, r_start+b.nrow-1, c_start, c_start+b.ncol-1);
+    }
+
+    pub fn tr_mul(&self, rhs:&Matrix) -> Matrix {
+        assert!(self.nrow == rhs.nrow, "Cannot multiply transpose of {}x{} by {}x{} matrix", self.nrow, self.ncol, rhs.nrow, rhs.ncol);
+        let out = gemm_tn(&self.data, &rhs.data, self.ncol, self.nrow, rhs.ncol, &GemmConfig::default()).unwrap();
+        return Matrix::new(self.ncol, rhs.ncol, out);
+    }
+
+    pub fn mul_tr(&self, rhs:&Matrix) -> Matrix {
+        assert!(self.ncol == rhs.ncol, "Cannot multiply {}x{} by transpose of {}x{} matrix", self.nrow, self.ncol, rhs.nrow, rhs.ncol);
+        let out = gemm_nt(&self.data, &rhs.data, self.nrow, self.ncol, rhs.nrow, &GemmConfig::default()).unwrap();
+        return Matrix::new(self.nrow, rhs.nrow, out);
+    }
+
+    // |a_ij - a_ji| <= tol for every pair.
+    pub fn is_symmetric(&self, tol:f64) -> bool {
+        if self.nrow != self.ncol {
+            return false;
+        }
+        for i in 0..self.nrow {
+            for j in i+1..self.ncol {
+                if (self[(i, j)]-self[(j, i)]).abs() > tol {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    // Largest absolute column sum.
+    pub fn norm1(&self) -> f64 {
+        let mut sums = vec![0.0;self.ncol];
+        for i in 0..self.nrow {
+            for (s, x) in sums.iter_mut().zip(self.row(i)) {
+                *s += x.abs();
+            }
+        }
+        return sums.iter().fold(0.0, |acc:f64, &s| acc.max(s));
+    }
+
+    // Largest absolute row sum.
+    pub fn norm_inf(&self) -> f64 {
+        return (0..self.nrow).fold(0.0, |acc:f64, i| acc.max(self.row(i).iter().map(|x| x.abs()).sum()));
+    }
+
+    pub fn norm(&self) -> f64 {
+        return norm(&self.data, self.data.len());
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = f64;
+
+    fn index(&self, (i, j):(usize, usize)) -> &f64 {
+        assert!(i < self.nrow && j < self.ncol, "Index ({}, {}) out of bounds for {}x{} matrix", i, j, self.nrow, self.ncol);
+        return &self.data[i*self.ncol+j];
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (i, j):(usize, usize)) -> &mut f64 {
+        assert!(i < self.nrow && j < self.ncol, "Index ({}, {}) out of bounds for {}x{} matrix", i, j, self.nrow, self.ncol);
+        return &mut self.data[i*self.ncol+j];
+    }
+}
+
+impl Mul<&Matrix> for &Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs:&Matrix) -> Matrix {
+        assert!(self.ncol == rhs.nrow, "Cannot multiply {}x{} by {}x{} matrix", self.nrow, self.ncol, rhs.nrow, rhs.ncol);
+        let out = matrix_multiply_simd(&self.data, &rhs.data, self.nrow, self.ncol, rhs.ncol).unwrap();
+        return Matrix::new(self.nrow, rhs.ncol, out);
+    }
+}
+
+impl Mul<Matrix> for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs:Matrix) -> Matrix {
+        return &self * &rhs;
+    }
+}
+
+impl Mul<f64> for &Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs:f64) -> Matrix {
+        let mut out = self.clone();
+        for x in out.data.iter_mut() {
+            *x *= rhs;
+        }
+        return out;
+    }
+}
+
+impl Add<&Matrix> for &Matrix {
+    type Output = Matrix;
+
+    fn add(self, rhs:&Matrix) -> Matrix {
+        assert!(self.shape() == rhs.shape(), "Cannot add {}x{} and {}x{} matrices", self.nrow, self.ncol, rhs.nrow, rhs.ncol);
+        let mut out = self.clone();
+        for i in 0..out.data.len() {
+            out.data[i] += rhs.data[i];
+        }
+        return out;
+    }
+}
+
+impl Add<Matrix> for Matrix {
+    type Output = Matrix;
+
+    fn add(self, rhs:Matrix) -> Matrix {
+        return &self + &rhs;
+    }
+}
+
+impl Sub<&Matrix> for &Matrix {
+    type Output = Matrix;
+
+    fn sub(self, rhs:&Matrix) -> Matrix {
+        assert!(self.shape() == rhs.shape(), "Cannot subtract {}x{} and {}x{} matrices", self.nrow, self.ncol, rhs.nrow, rhs.ncol);
+        let mut out = self.clone();
+        mul_sub_const(&rhs.data, &mut out.data, 1.0, rhs.data.len());
+        return out;
+    }
+}
+
+impl Sub<Matrix> for Matrix {
+    type Output = Matrix;
+
+    fn sub(self, rhs:Matrix) -> Matrix {
+        return &self - &rhs;
+    }
+}
@@ -1,95 +1,70 @@
-#![allow(dead_code)]
-use crate::qr_decomposition::*;
-use crate::matrix_utils::*;
-use rand_distr::{Distribution, Normal};
-use rand::thread_rng;
-
-pub fn eigenvalues(a:&[f64], n:usize) -> Vec<f64> {
-    let mut b = a.to_vec();
-    let mut eig = vec![0.0;n];
-
-    loop {
-        let s = b[n*n-1];
-        for i in 0..n {
-            b[i*n+i] -= s;
-        }
+#![allow(dead_code, clippy::needless_return)]
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::symmetric_eigen::{symmetric_eigen_with_options, symmetric_eigenvalues_with_options};
+use crate::general_eigen::general_eigen_with_options;
+use crate::convergence::{IterOptions, Convergence};
+
+// Symmetric input is routed to the tridiagonal QR solver, which returns ascending eigenvalues.
+fn symmetric_input(a:&Matrix) -> bool {
+    return a.is_symmetric(100.0*f64::EPSILON*a.norm_inf());
+}
 
-        let qr = householder_reflection_qr(&b, n, n);
-        b = matrix_multiply_simd(&qr.1, &qr.0, n, n, n);
+// Real eigenvalues of A. Non-symmetric input goes through the real Schur form, and a matrix
+// with complex eigenvalues is reported as an error rather than iterated on; use general_eigen
+// for those.
+pub fn eigenvalues(a:&Matrix) -> Result<Vec<f64>, LinalgError> {
+    let (w, conv) = eigenvalues_with_options(a, &IterOptions::default())?;
+    conv.check()?;
+    return Ok(w);
+}
 
-        let mut flag = true;
+// As eigenvalues, reporting convergence instead of failing when max_iter is reached.
+pub fn eigenvalues_with_options(a:&Matrix, options:&IterOptions) -> Result<(Vec<f64>, Convergence), LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
 
-        for i in 0..n {
-            b[i*n+i] += s;
-            if (b[i*n+i]-eig[i]).abs()/b[i*n+i].abs() > 0.001 {
-                flag = false;
-            }
-            eig[i] = b[i*n+i];
-        }
+    if symmetric_input(a) {
+        return symmetric_eigenvalues_with_options(a, options);
+    }
 
-        if flag {
-            break;
-        }
+    let eig = general_eigen_with_options(a, false, false, options)?;
+    if eig.convergence.converged && eig.values.iter().any(|v| !v.is_real()) {
+        return Err(LinalgError::ComplexEigenvalues);
     }
 
-    return eig;
+    return Ok((eig.values.iter().map(|v| v.re).collect(), eig.convergence));
 }
 
-pub fn eigenvectors(a:&[f64], n:usize)  -> (Vec<f64>, Vec<f64>) {
-    let mut eigvec = vec![0.0;n*n];
-    let mut eigval = vec![0.0;n];
+// Real eigenvalues of A with the eigenvectors as the columns of the matrix, unit 2-norm.
+pub fn eigenvectors(a:&Matrix)  -> Result<(Vec<f64>, Matrix), LinalgError> {
+    let (w, v, conv) = eigenvectors_with_options(a, &IterOptions::default())?;
+    conv.check()?;
+    return Ok((w, v));
+}
 
-    let mut b = a.to_vec();
+// As eigenvectors, reporting convergence instead of failing when max_iter is reached.
+pub fn eigenvectors_with_options(a:&Matrix, options:&IterOptions) -> Result<(Vec<f64>, Matrix, Convergence), LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
 
-    for i in 0..n {
-        eigvec[i*n+i] = 1.0;
+    if symmetric_input(a) {
+        return symmetric_eigen_with_options(a, options);
     }
 
-    loop {
-        let qr = householder_reflection_qr(&b, n, n);
-        eigvec = matrix_multiply_simd(&eigvec, &qr.0, n, n, n);
-
-        b = matrix_multiply_simd(&qr.1, &qr.0, n, n, n);
-
-        let mut flag = true;
+    let n = a.nrow;
+    let eig = general_eigen_with_options(a, false, true, options)?;
+    if eig.convergence.converged && eig.values.iter().any(|v| !v.is_real()) {
+        return Err(LinalgError::ComplexEigenvalues);
+    }
 
+    let right = eig.right.unwrap();
+    let mut eigvec = Matrix::zeros(n, n);
+    for j in 0..n {
         for i in 0..n {
-            if (b[i*n+i]-eigval[i]).abs()/b[i*n+i].abs() > 0.001 {
-                flag = false;
-            }
-            eigval[i] = b[i*n+i];
-        }
-
-        if flag {
-            break;
+            eigvec[(i, j)] = right[j][i].re;
         }
     }
 
-    return (eigval, eigvec);
-}
-
-pub fn run() {
-    let n = 5;
-    let m = 5;
-
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    
-    let mut a:Vec<f64> = vec![0.0;n*m];
-
-    for i in 0..n*m {
-        a[i] = normal.sample(&mut rng);
-    }
-
-    let b = matrix_multiply_simd(&a, &transpose(&a, n, m), n, m, n);
-    let eig = eigenvalues(&b, n);
-    let vecs = eigenvectors(&b, n);
-
-    println!("{:?}", b);
-    println!();
-    println!("{:?}", eig);
-    println!();
-    println!("{:?}", vecs.0);
-    println!();
-    println!("{:?}", vecs.1);
+    return Ok((eig.values.iter().map(|v| v.re).collect(), eigvec, eig.convergence));
 }
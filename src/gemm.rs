@@ -0,0 +1,284 @@
+#![allow(dead_code, clippy::needless_return)]
+use std::simd::prelude::*;
+use std::cmp::min;
+use rayon::prelude::*;
+use crate::linalg_error::LinalgError;
+
+// Register block of the micro-kernel: MR rows of A times NR columns of B.
+const MR:usize = 4;
+const NR:usize = 8;
+
+// Below this many multiply-adds the packing and thread start-up cost more than they save.
+pub const GEMM_MIN_FLOPS:usize = 32*32*32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GemmConfig {
+    pub mc: usize,
+    pub kc: usize,
+    pub nc: usize,
+}
+
+impl Default for GemmConfig {
+    fn default() -> Self {
+        Self {
+            mc: 96,
+            kc: 256,
+            nc: 512,
+        }
+    }
+}
+
+impl GemmConfig {
+    pub fn new(mc:usize, kc:usize, nc:usize) -> Self {
+        Self {
+            mc,
+            kc,
+            nc,
+        }
+    }
+
+    // Block sizes are rounded up to whole micro-panels so that tiles never share a panel.
+    fn rounded(&self) -> (usize, usize, usize) {
+        let mc = self.mc.max(1).div_ceil(MR)*MR;
+        let nc = self.nc.max(1).div_ceil(NR)*NR;
+        return (mc, self.kc.max(1), nc);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SendPtr(*mut f64);
+
+unsafe impl Send for SendPtr {}
+unsafe impl Sync for SendPtr {}
+
+impl SendPtr {
+    fn get(&self) -> *mut f64 {
+        return self.0;
+    }
+}
+
+fn pack_a(a:&[f64], a_rs:usize, a_cs:usize, n:usize, pc:usize, kb:usize, packed:&mut [f64]) {
+    packed.par_chunks_mut(MR*kb).enumerate().for_each(|(ip, panel)| {
+        let i0 = ip*MR;
+        let rows = min(MR, n-i0);
+        for k in 0..kb {
+            for r in 0..MR {
+                panel[k*MR+r] = if r < rows { a[(i0+r)*a_rs+(pc+k)*a_cs] } else { 0.0 };
+            }
+        }
+    });
+}
+
+fn pack_b(b:&[f64], b_rs:usize, b_cs:usize, p:usize, pc:usize, kb:usize, packed:&mut [f64]) {
+    packed.par_chunks_mut(NR*kb).enumerate().for_each(|(jp, panel)| {
+        let j0 = jp*NR;
+        let cols = min(NR, p-j0);
+        for k in 0..kb {
+            for c in 0..NR {
+                panel[k*NR+c] = if c < cols { b[(pc+k)*b_rs+(j0+c)*b_cs] } else { 0.0 };
+            }
+        }
+    });
+}
+
+// Accumulates one MR x NR block of C in registers. The accumulators are seeded from C so that
+// every entry is summed over k in the same order as the plain i-k-j loop.
+#[allow(clippy::needless_range_loop)]
+unsafe fn micro_kernel(pa:&[f64], pb:&[f64], kb:usize, c:*mut f64, ldc:usize, rows:usize, cols:usize) {
+    let mut acc = [Simd::<f64, NR>::splat(0.0);MR];
+
+    for r in 0..rows {
+        let mut tmp = [0.0;NR];
+        for j in 0..cols {
+            tmp[j] = *c.add(r*ldc+j);
+        }
+        acc[r] = Simd::from_array(tmp);
+    }
+
+    for (a, b) in pa[0..kb*MR].chunks_exact(MR).zip(pb[0..kb*NR].chunks_exact(NR)) {
+        let b:Simd<f64, NR> = Simd::from_slice(b);
+        for r in 0..MR {
+            acc[r] += Simd::splat(a[r])*b;
+        }
+    }
+
+    for r in 0..rows {
+        let tmp = acc[r].to_array();
+        for j in 0..cols {
+            *c.add(r*ldc+j) = tmp[j];
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn gemm_strided(a:&[f64], a_rs:usize, a_cs:usize, b:&[f64], b_rs:usize, b_cs:usize, n:usize, m:usize, p:usize, config:&GemmConfig) -> Vec<f64> {
+    let mut out:Vec<f64> = vec![0.0;n*p];
+
+    if n == 0 || m == 0 || p == 0 {
+        return out;
+    }
+
+    if n*m*p < GEMM_MIN_FLOPS {
+        for i in 0..n {
+            for k in 0..m {
+                let x = a[i*a_rs+k*a_cs];
+                for j in 0..p {
+                    out[i*p+j] += x*b[k*b_rs+j*b_cs];
+                }
+            }
+        }
+        return out;
+    }
+
+    let (mc, kc, nc) = config.rounded();
+    let n_panels = n.div_ceil(MR);
+    let p_panels = p.div_ceil(NR);
+
+    let mut packed_a:Vec<f64> = vec![0.0;n_panels*MR*min(kc, m)];
+    let mut packed_b:Vec<f64> = vec![0.0;p_panels*NR*min(kc, m)];
+
+    let mut tiles:Vec<(usize, usize)> = Vec::new();
+    for ic in (0..n).step_by(mc) {
+        for jc in (0..p).step_by(nc) {
+            tiles.push((ic, jc));
+        }
+    }
+
+    let c_ptr = SendPtr(out.as_mut_ptr());
+
+    for pc in (0..m).step_by(kc) {
+        let kb = min(kc, m-pc);
+        let pa = &mut packed_a[0..n_panels*MR*kb];
+        let pb = &mut packed_b[0..p_panels*NR*kb];
+
+        pack_a(a, a_rs, a_cs, n, pc, kb, pa);
+        pack_b(b, b_rs, b_cs, p, pc, kb, pb);
+
+        let pa = &*pa;
+        let pb = &*pb;
+
+        tiles.par_iter().for_each(|&(ic, jc)| {
+            let i_end = min(ic+mc, n);
+            let j_end = min(jc+nc, p);
+
+            for jr in (jc..j_end).step_by(NR) {
+                let jp = jr/NR;
+                let cols = min(NR, p-jr);
+                let b_panel = &pb[jp*NR*kb..(jp+1)*NR*kb];
+
+                for ir in (ic..i_end).step_by(MR) {
+                    let ip = ir/MR;
+                    let rows = min(MR, n-ir);
+                    let a_panel = &pa[ip*MR*kb..(ip+1)*MR*kb];
+
+                    // Tiles are disjoint blocks of C, so no two threads write the same entry.
+                    unsafe {
+                        micro_kernel(a_panel, b_panel, kb, c_ptr.get().add(ir*p+jr), p, rows, cols);
+                    }
+                }
+            }
+        });
+    }
+
+    return out;
+}
+
+fn check_len(a:&[f64], nrow:usize, ncol:usize) -> Result<(), LinalgError> {
+    if a.len() != nrow*ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (nrow, ncol), found: (a.len()/ncol.max(1), ncol) });
+    }
+    return Ok(());
+}
+
+// C = A*B where A is n x m and B is m x p.
+pub fn gemm(a:&[f64], b:&[f64], n:usize, m:usize, p:usize, config:&GemmConfig) -> Result<Vec<f64>, LinalgError> {
+    check_len(a, n, m)?;
+    check_len(b, m, p)?;
+    return Ok(gemm_strided(a, m, 1, b, p, 1, n, m, p, config));
+}
+
+// C = A'*B where A is stored as m x n and B is m x p.
+pub fn gemm_tn(a:&[f64], b:&[f64], n:usize, m:usize, p:usize, config:&GemmConfig) -> Result<Vec<f64>, LinalgError> {
+    check_len(a, m, n)?;
+    check_len(b, m, p)?;
+    return Ok(gemm_strided(a, 1, n, b, p, 1, n, m, p, config));
+}
+
+// C = A*B' where A is n x m and B is stored as p x m.
+pub fn gemm_nt(a:&[f64], b:&[f64], n:usize, m:usize, p:usize, config:&GemmConfig) -> Result<Vec<f64>, LinalgError> {
+    check_len(a, n, m)?;
+    check_len(b, p, m)?;
+    return Ok(gemm_strided(a, m, 1, b, 1, m, n, m, p, config));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(len:usize, seed:u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    }
+
+    fn naive(a:&[f64], b:&[f64], n:usize, m:usize, p:usize) -> Vec<f64> {
+        let mut out = vec![0.0;n*p];
+        for i in 0..n {
+            for k in 0..m {
+                for j in 0..p {
+                    out[i*p+j] += a[i*m+k]*b[k*p+j];
+                }
+            }
+        }
+        return out;
+    }
+
+    fn transpose(a:&[f64], n:usize, m:usize) -> Vec<f64> {
+        let mut out = vec![0.0;n*m];
+        for i in 0..n {
+            for j in 0..m {
+                out[j*n+i] = a[i*m+j];
+            }
+        }
+        return out;
+    }
+
+    fn assert_close(x:&[f64], y:&[f64], tol:f64) {
+        assert_eq!(x.len(), y.len());
+        for (u, v) in x.iter().zip(y.iter()) {
+            assert!((u-v).abs() <= tol, "{} vs {}", u, v);
+        }
+    }
+
+    #[test]
+    fn gemm_matches_naive_on_ragged_shapes() {
+        // Small blocks force several k-panels and partial micro-tiles on every edge.
+        let config = GemmConfig::new(7, 5, 11);
+        for &(n, m, p) in &[(1, 1, 1), (33, 37, 41), (64, 64, 64), (5, 130, 3), (70, 1, 90)] {
+            let a = random(n*m, 1);
+            let b = random(m*p, 2);
+            let expected = naive(&a, &b, n, m, p);
+            assert_close(&gemm(&a, &b, n, m, p, &config).unwrap(), &expected, 1e-12);
+            assert_close(&gemm(&a, &b, n, m, p, &GemmConfig::default()).unwrap(), &expected, 1e-12);
+        }
+    }
+
+    #[test]
+    fn transposed_variants_match_naive() {
+        let (n, m, p) = (45, 38, 29);
+        let a = random(n*m, 3);
+        let b = random(m*p, 4);
+        let expected = naive(&a, &b, n, m, p);
+        let config = GemmConfig::new(8, 16, 8);
+        assert_close(&gemm_tn(&transpose(&a, n, m), &b, n, m, p, &config).unwrap(), &expected, 1e-12);
+        assert_close(&gemm_nt(&a, &transpose(&b, m, p), n, m, p, &config).unwrap(), &expected, 1e-12);
+    }
+
+    #[test]
+    fn gemm_handles_empty_and_rejects_bad_lengths() {
+        assert_eq!(gemm(&[], &[], 3, 0, 4, &GemmConfig::default()).unwrap(), vec![0.0;12]);
+        assert!(matches!(gemm(&[1.0;5], &[1.0;6], 2, 3, 2, &GemmConfig::default()),
+            Err(LinalgError::DimensionMismatch { .. })));
+    }
+}
@@ -0,0 +1,628 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::orthogonal_matrices::*;
+use crate::complex::Complex;
+use crate::convergence::{IterOptions, Convergence};
+
+// Francis steps allowed per eigenvalue before giving up.
+const SWEEPS_PER_EIGENVALUE:usize = 30;
+
+// Steps without a deflation after which an exceptional shift is tried.
+const EXCEPTIONAL_SHIFT:usize = 10;
+
+// Eigenvalues of a real matrix in the order they appear on the diagonal of its real Schur form;
+// complex conjugate pairs are adjacent, positive imaginary part first. right[k] satisfies
+// Av = values[k] v and left[k] satisfies u^H A = values[k] u^H; both have unit 2-norm with the
+// largest component real. When convergence reports failure, values and vectors belonging to
+// the unreduced part of the Schur form are not meaningful.
+#[derive(Clone, Debug)]
+pub struct GeneralEigen {
+    pub values: Vec<Complex>,
+    pub right: Option<Vec<Vec<Complex>>>,
+    pub left: Option<Vec<Vec<Complex>>>,
+    pub convergence: Convergence,
+}
+
+// A = QHQ' with H upper Hessenberg and Q orthogonal.
+pub fn hessenberg(a:&Matrix) -> Result<(Matrix, Matrix), LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
+
+    let n = a.nrow;
+    let mut h = a.clone();
+    let mut tau = vec![0.0;n.saturating_sub(1)];
+
+    for i in 0..n.saturating_sub(2) {
+        let mut x = h.view().subview(i+1..n, i..i+1).to_vec();
+        tau[i] = householder_reflector(&mut x);
+        let beta = x[0];
+        x[0] = 1.0;
+
+        apply_reflector_left(h.view_mut().subview_mut(i+1..n, i+1..n), &x, tau[i]);
+        apply_reflector_right(h.view_mut().subview_mut(0..n, i+1..n), &x, tau[i]);
+
+        x[0] = beta;
+        for k in 0..x.len() {
+            h[(i+1+k, i)] = x[k];
+        }
+    }
+
+    let mut q = Matrix::identity(n);
+    if n > 1 {
+        let q1 = form_q(h.view().subview(1..n, 0..n-1), &tau[0..n-2], n-1);
+        q.view_mut().subview_mut(1..n, 1..n).copy_from(q1.view());
+    }
+
+    for j in 0..n {
+        for i in j+2..n {
+            h[(i, j)] = 0.0;
+        }
+    }
+
+    return Ok((h, q));
+}
+
+// Rows k and k+1 of t from column c0 on, and columns k and k+1 of t up to row r1 and of z,
+// rotated by G = [c -s; s c], so that t becomes G'tG.
+fn rotate_pair(t:&mut Matrix, z:&mut Matrix, k:usize, c:f64, s:f64, c0:usize, r1:usize) {
+    let n = t.nrow;
+    for j in c0..n {
+        let x = t[(k, j)];
+        let y = t[(k+1, j)];
+        t[(k, j)] = c*x+s*y;
+        t[(k+1, j)] = -s*x+c*y;
+    }
+    for i in 0..r1 {
+        let x = t[(i, k)];
+        let y = t[(i, k+1)];
+        t[(i, k)] = c*x+s*y;
+        t[(i, k+1)] = -s*x+c*y;
+    }
+    for i in 0..n {
+        let x = z[(i, k)];
+        let y = z[(i, k+1)];
+        z[(i, k)] = c*x+s*y;
+        z[(i, k+1)] = -s*x+c*y;
+    }
+}
+
+// Splits the deflated 2x2 block at k into two 1x1 blocks when its eigenvalues are real.
+fn standardize_block(t:&mut Matrix, z:&mut Matrix, k:usize) {
+    let (a, b) = (t[(k, k)], t[(k, k+1)]);
+    let (c, d) = (t[(k+1, k)], t[(k+1, k+1)]);
+    if c == 0.0 {
+        return;
+    }
+
+    let p = 0.5*(a-d);
+    let disc = p*p+b*c;
+    if disc < 0.0 {
+        return;
+    }
+
+    // Eigenvector of the block for its eigenvalue farthest from the mean, which is the
+    // better conditioned of the two candidates below.
+    let lambda = 0.5*(a+d)+if p >= 0.0 { disc.sqrt() } else { -disc.sqrt() };
+    let (v1, v2) = ((b, lambda-a), (lambda-d, c));
+    let (x, y) = if v1.0.hypot(v1.1) >= v2.0.hypot(v2.1) { v1 } else { v2 };
+    let r = x.hypot(y);
+
+    rotate_pair(t, z, k, x/r, y/r, k, k+2);
+    t[(k+1, k)] = 0.0;
+}
+
+// Rows r0.. of t from column c0 on, times the small reflector I - tau vv' from the left.
+fn reflect_rows(t:&mut Matrix, r0:usize, c0:usize, v:&[f64], tau:f64) {
+    if tau == 0.0 {
+        return;
+    }
+    for j in c0..t.ncol {
+        let mut s = 0.0;
+        for q in 0..v.len() {
+            s += v[q]*t[(r0+q, j)];
+        }
+        s *= tau;
+        for q in 0..v.len() {
+            t[(r0+q, j)] -= s*v[q];
+        }
+    }
+}
+
+// Columns c0.. of t up to row r1, times the small reflector I - tau vv' from the right.
+fn reflect_cols(t:&mut Matrix, c0:usize, r1:usize, v:&[f64], tau:f64) {
+    if tau == 0.0 {
+        return;
+    }
+    for i in 0..r1 {
+        let row = &mut t.row_mut(i)[c0..c0+v.len()];
+        let mut s = 0.0;
+        for q in 0..v.len() {
+            s += v[q]*row[q];
+        }
+        s *= tau;
+        for q in 0..v.len() {
+            row[q] -= s*v[q];
+        }
+    }
+}
+
+// One Francis double-shift step on the unreduced block l..=h (at least 3x3), applied to the
+// whole of t so that it converges to the real Schur form, and accumulated into z.
+fn francis_step(t:&mut Matrix, z:&mut Matrix, l:usize, h:usize, exceptional:bool) {
+    let n = t.nrow;
+
+    // Shifts are the eigenvalues of the trailing 2x2 block, entering only through their sum s
+    // and product p; the exceptional shifts break cycles that the standard ones can fall into.
+    let (s, p) = if exceptional {
+        let w = t[(h, h-1)].abs()+t[(h-1, h-2)].abs();
+        let h11 = 0.75*w+t[(h, h)];
+        (2.0*h11, h11*h11+0.4375*w*w)
+    }
+    else {
+        (t[(h-1, h-1)]+t[(h, h)], t[(h-1, h-1)]*t[(h, h)]-t[(h-1, h)]*t[(h, h-1)])
+    };
+
+    // First column of (H - s1 I)(H - s2 I), which has three non-zeros.
+    let mut x = t[(l, l)]*t[(l, l)]+t[(l, l+1)]*t[(l+1, l)]-s*t[(l, l)]+p;
+    let mut y = t[(l+1, l)]*(t[(l, l)]+t[(l+1, l+1)]-s);
+    let mut w = t[(l+1, l)]*t[(l+2, l+1)];
+
+    for k in l..h-1 {
+        let mut v = [x, y, w];
+        let tau = householder_reflector(&mut v);
+        let beta = v[0];
+        v[0] = 1.0;
+
+        let c0 = if k > l { k-1 } else { l };
+        let r1 = (k+4).min(h+1);
+        reflect_rows(t, k, c0, &v, tau);
+        reflect_cols(t, k, r1, &v, tau);
+        reflect_cols(z, k, n, &v, tau);
+
+        if k > l {
+            t[(k, k-1)] = beta;
+            t[(k+1, k-1)] = 0.0;
+            t[(k+2, k-1)] = 0.0;
+        }
+
+        x = t[(k+1, k)];
+        y = t[(k+2, k)];
+        if k+3 <= h {
+            w = t[(k+3, k)];
+        }
+    }
+
+    let mut v = [x, y];
+    let tau = householder_reflector(&mut v);
+    let beta = v[0];
+    v[0] = 1.0;
+
+    reflect_rows(t, h-1, h-2, &v, tau);
+    reflect_cols(t, h-1, h+1, &v, tau);
+    reflect_cols(z, h-1, n, &v, tau);
+
+    t[(h-1, h-2)] = beta;
+    t[(h, h-2)] = 0.0;
+}
+
+// A = ZTZ' with Z orthogonal and T quasi upper triangular: 1x1 diagonal blocks hold the real
+// eigenvalues and 2x2 blocks the complex conjugate pairs.
+pub fn real_schur(a:&Matrix) -> Result<(Matrix, Matrix), LinalgError> {
+    let (t, z, conv) = real_schur_with_options(a, &IterOptions::default())?;
+    conv.check()?;
+    return Ok((t, z));
+}
+
+// As real_schur, reporting convergence instead of failing when max_iter is reached.
+// Subdiagonals at or below tol*(|t_i-1,i-1| + |t_ii|) are dropped; max_iter defaults to 30
+// Francis steps per eigenvalue.
+pub fn real_schur_with_options(a:&Matrix, options:&IterOptions) -> Result<(Matrix, Matrix, Convergence), LinalgError> {
+    let (mut t, mut z) = hessenberg(a)?;
+    let n = t.nrow;
+
+    let tnorm = t.norm_inf();
+    let tol = options.tol_or(f64::EPSILON);
+    let max_iter = options.max_iter_or(SWEEPS_PER_EIGENVALUE*n);
+    let mut iter = 0;
+    let mut residual:f64 = 0.0;
+    let mut since_deflation = 0;
+    let mut end = n;
+
+    while end > 0 {
+        let h = end-1;
+
+        let mut l = h;
+        while l > 0 {
+            let mut s = t[(l-1, l-1)].abs()+t[(l, l)].abs();
+            if s == 0.0 {
+                s = tnorm;
+            }
+            if t[(l, l-1)].abs() <= tol*s {
+                residual = residual.max(t[(l, l-1)].abs());
+                t[(l, l-1)] = 0.0;
+                break;
+            }
+            l -= 1;
+        }
+
+        if l == h {
+            end -= 1;
+            since_deflation = 0;
+            continue;
+        }
+
+        if l+1 == h {
+            standardize_block(&mut t, &mut z, l);
+            end -= 2;
+            since_deflation = 0;
+            continue;
+        }
+
+        if iter == max_iter {
+            let left = (1..end).fold(residual, |acc, i| acc.max(t[(i, i-1)].abs()));
+            return Ok((t, z, Convergence { iterations: iter, residual: left, converged: false }));
+        }
+        iter += 1;
+        since_deflation += 1;
+
+        francis_step(&mut t, &mut z, l, h, since_deflation % EXCEPTIONAL_SHIFT == 0);
+    }
+
+    return Ok((t, z, Convergence { iterations: iter, residual, converged: true }));
+}
+
+// Eigenvalues read off the diagonal blocks of a real Schur form.
+fn schur_eigenvalues(t:&Matrix) -> Vec<Complex> {
+    let n = t.nrow;
+    let mut out = Vec::with_capacity(n);
+    let mut i = 0;
+
+    while i < n {
+        if i+1 < n && t[(i+1, i)] != 0.0 {
+            let (a, b) = (t[(i, i)], t[(i, i+1)]);
+            let (c, d) = (t[(i+1, i)], t[(i+1, i+1)]);
+            let p = 0.5*(a-d);
+            let re = 0.5*(a+d);
+            let im = (-(p*p+b*c)).max(0.0).sqrt();
+            out.push(Complex::new(re, im));
+            out.push(Complex::new(re, -im));
+            i += 2;
+        }
+        else {
+            out.push(Complex::from_real(t[(i, i)]));
+            i += 1;
+        }
+    }
+
+    return out;
+}
+
+// Solves the 2x2 system mx = r by elimination with partial pivoting, with pivots smaller than
+// smin perturbed to smin so that nearly repeated eigenvalues still give a finite vector.
+fn solve_2x2(m:[[Complex;2];2], r:[Complex;2], smin:f64) -> [Complex;2] {
+    let (m, r) = if m[0][0].abs() >= m[1][0].abs() { (m, r) } else { ([m[1], m[0]], [r[1], r[0]]) };
+
+    let mut u00 = m[0][0];
+    if u00.abs() < smin {
+        u00 = Complex::from_real(smin);
+    }
+    let f = m[1][0]/u00;
+    let mut u11 = m[1][1]-f*m[0][1];
+    if u11.abs() < smin {
+        u11 = Complex::from_real(smin);
+    }
+
+    let x1 = (r[1]-f*r[0])/u11;
+    let x0 = (r[0]-m[0][1]*x1)/u00;
+    return [x0, x1];
+}
+
+fn solve_1x1(m:Complex, r:Complex, smin:f64) -> Complex {
+    if m.abs() < smin {
+        return r/smin;
+    }
+    return r/m;
+}
+
+// Rescales x when it is about to overflow; eigenvectors are normalized afterwards anyway.
+fn rescale(x:&mut [Complex]) {
+    let big = x.iter().fold(0.0, |acc:f64, v| acc.max(v.abs()));
+    if big > 1e100 {
+        for v in x.iter_mut() {
+            *v = *v/big;
+        }
+    }
+}
+
+// Size of the diagonal block of t that starts at row i.
+fn block_size(t:&Matrix, i:usize) -> usize {
+    if i+1 < t.nrow && t[(i+1, i)] != 0.0 {
+        return 2;
+    }
+    return 1;
+}
+
+// Right eigenvector x of T for the eigenvalue lambda of the diagonal block at k, by back
+// substitution through the blocks above it.
+fn schur_right_vector(t:&Matrix, k:usize, lambda:Complex, smin:f64) -> Vec<Complex> {
+    let n = t.nrow;
+    let mut x = vec![Complex::default();n];
+    let bs = block_size(t, k);
+
+    if bs == 1 {
+        x[k] = Complex::from_real(1.0);
+    }
+    else {
+        x[k+1] = Complex::from_real(1.0);
+        x[k] = -(Complex::from_real(t[(k+1, k+1)])-lambda)/t[(k+1, k)];
+    }
+    let top = k+bs;
+
+    let mut i = k;
+    while i > 0 {
+        let two = i >= 2 && t[(i-1, i-2)] != 0.0;
+        let r = if two { i-2 } else { i-1 };
+
+        let mut rhs = [Complex::default();2];
+        for q in 0..(if two { 2 } else { 1 }) {
+            for j in i..top {
+                rhs[q] -= x[j]*t[(r+q, j)];
+            }
+        }
+
+        if two {
+            let m = [
+                [Complex::from_real(t[(r, r)])-lambda, Complex::from_real(t[(r, r+1)])],
+                [Complex::from_real(t[(r+1, r)]), Complex::from_real(t[(r+1, r+1)])-lambda],
+            ];
+            let s = solve_2x2(m, rhs, smin);
+            x[r] = s[0];
+            x[r+1] = s[1];
+        }
+        else {
+            x[r] = solve_1x1(Complex::from_real(t[(r, r)])-lambda, rhs[0], smin);
+        }
+
+        rescale(&mut x[r..top]);
+        i = r;
+    }
+
+    return x;
+}
+
+// Left eigenvector w of T, w'T = lambda w', for the eigenvalue lambda of the diagonal block at
+// k, by forward substitution through the blocks below it.
+fn schur_left_vector(t:&Matrix, k:usize, lambda:Complex, smin:f64) -> Vec<Complex> {
+    let n = t.nrow;
+    let mut w = vec![Complex::default();n];
+    let bs = block_size(t, k);
+
+    w[k] = Complex::from_real(1.0);
+    if bs == 2 {
+        w[k+1] = -(Complex::from_real(t[(k, k)])-lambda)/t[(k+1, k)];
+    }
+
+    let mut c = k+bs;
+    while c < n {
+        let cb = block_size(t, c);
+
+        let mut rhs = [Complex::default();2];
+        for q in 0..cb {
+            for i in k..c {
+                rhs[q] -= w[i]*t[(i, c+q)];
+            }
+        }
+
+        if cb == 2 {
+            let m = [
+                [Complex::from_real(t[(c, c)])-lambda, Complex::from_real(t[(c+1, c)])],
+                [Complex::from_real(t[(c, c+1)]), Complex::from_real(t[(c+1, c+1)])-lambda],
+            ];
+            let s = solve_2x2(m, rhs, smin);
+            w[c] = s[0];
+            w[c+1] = s[1];
+        }
+        else {
+            w[c] = solve_1x1(Complex::from_real(t[(c, c)])-lambda, rhs[0], smin);
+        }
+
+        rescale(&mut w[k..c+cb]);
+        c += cb;
+    }
+
+    return w;
+}
+
+// Zx normalized to unit 2-norm with its largest component real and positive.
+#[allow(clippy::needless_range_loop)]
+fn back_transform(z:&Matrix, x:&[Complex]) -> Vec<Complex> {
+    let n = z.nrow;
+    let mut v = vec![Complex::default();n];
+    for i in 0..n {
+        let row = z.row(i);
+        for j in 0..n {
+            if x[j] != Complex::default() {
+                v[i] += x[j]*row[j];
+            }
+        }
+    }
+
+    let mut p = 0;
+    for i in 0..n {
+        if v[i].abs() > v[p].abs() {
+            p = i;
+        }
+    }
+    let nrm = v.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+    if nrm == 0.0 {
+        return v;
+    }
+    let phase = v[p].conj()/(v[p].abs()*nrm);
+    for c in v.iter_mut() {
+        *c *= phase;
+    }
+    return v;
+}
+
+// Eigenvalues of a general real matrix via Hessenberg reduction and Francis double-shift QR.
+pub fn general_eigenvalues(a:&Matrix) -> Result<Vec<Complex>, LinalgError> {
+    let (t, _) = real_schur(a)?;
+    return Ok(schur_eigenvalues(&t));
+}
+
+// Eigenvalues and, on request, left and right eigenvectors of a general real matrix. The
+// vectors are computed from the real Schur form by substitution and mapped back through the
+// Schur vectors; those of a conjugate pair are conjugates of each other.
+pub fn general_eigen(a:&Matrix, want_left:bool, want_right:bool) -> Result<GeneralEigen, LinalgError> {
+    let eig = general_eigen_with_options(a, want_left, want_right, &IterOptions::default())?;
+    eig.convergence.check()?;
+    return Ok(eig);
+}
+
+// As general_eigen, reporting convergence in the result instead of failing.
+pub fn general_eigen_with_options(a:&Matrix, want_left:bool, want_right:bool, options:&IterOptions) -> Result<GeneralEigen, LinalgError> {
+    let (t, z, convergence) = real_schur_with_options(a, options)?;
+    let n = t.nrow;
+    let values = schur_eigenvalues(&t);
+
+    let smin = (f64::EPSILON*t.norm_inf()).max(f64::MIN_POSITIVE);
+    let mut right = if want_right { Some(Vec::with_capacity(n)) } else { None };
+    let mut left = if want_left { Some(Vec::with_capacity(n)) } else { None };
+
+    let mut k = 0;
+    while k < n {
+        let bs = block_size(&t, k);
+        let lambda = values[k];
+
+        if let Some(right) = right.as_mut() {
+            let v = back_transform(&z, &schur_right_vector(&t, k, lambda, smin));
+            if bs == 2 {
+                let vc = v.iter().map(|c| c.conj()).collect();
+                right.push(v);
+                right.push(vc);
+            }
+            else {
+                right.push(v);
+            }
+        }
+
+        // w'T = lambda w' gives (Zw)'A = lambda (Zw)', so u = conj(Zw) has u^H A = lambda u^H.
+        if let Some(left) = left.as_mut() {
+            let u:Vec<Complex> = back_transform(&z, &schur_left_vector(&t, k, lambda, smin)).iter().map(|c| c.conj()).collect();
+            if bs == 2 {
+                let uc = u.iter().map(|c| c.conj()).collect();
+                left.push(u);
+                left.push(uc);
+            }
+            else {
+                left.push(u);
+            }
+        }
+
+        k += bs;
+    }
+
+    return Ok(GeneralEigen {
+        values,
+        right,
+        left,
+        convergence,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    // max_i |(Av - lambda v)_i|, or of u^H A - lambda u^H when left is set.
+    fn residual(a:&Matrix, v:&[Complex], lambda:Complex, left:bool) -> f64 {
+        let n = a.nrow;
+        let mut out:f64 = 0.0;
+        for i in 0..n {
+            let mut s = Complex::from_real(0.0);
+            for j in 0..n {
+                if left {
+                    s += v[j].conj()*a[(j, i)];
+                }
+                else {
+                    s += v[j]*a[(i, j)];
+                }
+            }
+            let lv = if left { lambda*v[i].conj() } else { lambda*v[i] };
+            out = out.max((s-lv).abs());
+        }
+        return out;
+    }
+
+    #[test]
+    fn hessenberg_and_schur_reconstruct() {
+        let n = 15;
+        let a = random(n, n, 1);
+
+        let (h, q) = hessenberg(&a).unwrap();
+        assert!(max_abs_diff(&(q.clone()*h.clone()).mul_tr(&q), &a) < 1e-12);
+        for i in 2..n {
+            for j in 0..i-1 {
+                assert_eq!(h[(i, j)], 0.0);
+            }
+        }
+
+        let (t, z) = real_schur(&a).unwrap();
+        assert!(max_abs_diff(&z.tr_mul(&z), &Matrix::identity(n)) < 1e-12);
+        assert!(max_abs_diff(&(z.clone()*t.clone()).mul_tr(&z), &a) < 1e-12);
+        for i in 1..n {
+            // No two consecutive subdiagonals, and every 2x2 block holds a complex pair.
+            if t[(i, i-1)] != 0.0 {
+                assert!(i+1 == n || t[(i+1, i)] == 0.0);
+                let p = 0.5*(t[(i-1, i-1)]-t[(i, i)]);
+                assert!(p*p+t[(i-1, i)]*t[(i, i-1)] < 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn right_and_left_eigenvectors_have_small_residuals() {
+        for seed in 2..6 {
+            let n = 12;
+            let a = random(n, n, seed);
+            let eig = general_eigen(&a, true, true).unwrap();
+            let right = eig.right.unwrap();
+            let left = eig.left.unwrap();
+            assert_eq!(eig.values.len(), n);
+            for k in 0..n {
+                assert!(residual(&a, &right[k], eig.values[k], false) < 1e-12);
+                assert!(residual(&a, &left[k], eig.values[k], true) < 1e-12);
+                let nrm:f64 = right[k].iter().map(|c| c.norm_sqr()).sum();
+                assert!((nrm-1.0).abs() < 1e-12);
+            }
+
+            let trace:f64 = (0..n).map(|i| a[(i, i)]).sum();
+            let sum = eig.values.iter().fold(Complex::from_real(0.0), |acc, &v| acc+v);
+            assert!((sum.re-trace).abs() < 1e-12 && sum.im.abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn rotation_has_conjugate_pair() {
+        let a = Matrix::new(3, 3, vec![0.0, -2.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 3.0]);
+        let mut values = general_eigenvalues(&a).unwrap();
+        values.sort_by(|p, q| p.im.total_cmp(&q.im));
+        assert!((values[0]-Complex::new(0.0, -2.0)).abs() < 1e-14);
+        assert!((values[1]-Complex::from_real(3.0)).abs() < 1e-14);
+        assert!((values[2]-Complex::new(0.0, 2.0)).abs() < 1e-14);
+
+        assert!(matches!(crate::eigenvalues::eigenvalues(&a), Err(LinalgError::ComplexEigenvalues)));
+    }
+}
@@ -0,0 +1,357 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::convergence::{IterOptions, Convergence};
+use rayon::prelude::*;
+
+// Sweeps allowed before giving up; Jacobi typically needs fewer than ten.
+const MAX_SWEEPS:usize = 30;
+
+// Round r of the circle method on m (even) indices: m/2 disjoint pairs, and over m-1 rounds
+// every pair exactly once. Rotations on disjoint pairs commute, so a round runs in parallel.
+fn round_robin(m:usize, r:usize) -> Vec<(usize, usize)> {
+    let k = m-1;
+    let mut out = Vec::with_capacity(m/2);
+    out.push((r%k, k));
+    for i in 1..m/2 {
+        let p = (r+i)%k;
+        let q = (r+k-i)%k;
+        out.push((p.min(q), p.max(q)));
+    }
+    return out;
+}
+
+// Disjoint mutable rows p and q of a row-major matrix for every pair.
+fn row_pairs<'a>(data:&'a mut [f64], ncol:usize, pairs:&[(usize, usize)]) -> Vec<(&'a mut [f64], &'a mut [f64])> {
+    let mut rows:Vec<Option<&mut [f64]>> = data.chunks_mut(ncol).map(Some).collect();
+    return pairs.iter().map(|&(p, q)| (rows[p].take().unwrap(), rows[q].take().unwrap())).collect();
+}
+
+// x = cx - sy, y = sx + cy
+fn rotate(x:&mut [f64], y:&mut [f64], c:f64, s:f64) {
+    for k in 0..x.len() {
+        let u = x[k];
+        let v = y[k];
+        x[k] = c*u-s*v;
+        y[k] = s*u+c*v;
+    }
+}
+
+// Rotation (c, s, t) annihilating the off-diagonal g of the symmetric 2x2 [a g; g b], with
+// t = tan of the angle taken as the smaller root so the rotation stays close to the identity.
+fn jacobi_rotation(a:f64, b:f64, g:f64) -> (f64, f64, f64) {
+    let theta = (b-a)/(2.0*g);
+    let t = if theta.abs() > 1e150 {
+        0.5/theta
+    }
+    else {
+        let t = 1.0/(theta.abs()+theta.hypot(1.0));
+        if theta < 0.0 { -t } else { t }
+    };
+    let c = 1.0/t.hypot(1.0);
+    return (c, t*c, t);
+}
+
+// A = V diag(w) V' for symmetric A by cyclic two-sided Jacobi, with w ascending. A pair is
+// rotated while |a_pq| > tol*sqrt(|a_pp a_qq|), which keeps small eigenvalues of well scaled
+// matrices to high relative accuracy; tol defaults to n*eps and max_iter counts sweeps.
+// Only the lower triangle of A is read.
+pub fn jacobi_eigen(a:&Matrix, options:&IterOptions) -> Result<(Vec<f64>, Matrix, Convergence), LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
+
+    let n = a.nrow;
+    let mut w = a.clone();
+    for i in 0..n {
+        for j in i+1..n {
+            w[(i, j)] = w[(j, i)];
+        }
+    }
+
+    let mut vt = Matrix::identity(n);
+    let tol = options.tol_or((n as f64)*f64::EPSILON);
+    let max_sweeps = options.max_iter_or(MAX_SWEEPS);
+    let m = n+n%2;
+
+    let mut sweeps = 0;
+    let mut residual:f64 = 0.0;
+    let mut converged = n < 2;
+
+    while !converged && sweeps < max_sweeps {
+        let mut rotated = false;
+        residual = 0.0;
+
+        for r in 0..m-1 {
+            let mut rots = vec![];
+            for (p, q) in round_robin(m, r) {
+                if q >= n {
+                    continue;
+                }
+                let g = w[(p, q)];
+                if g == 0.0 {
+                    continue;
+                }
+                if g.abs() <= tol*(w[(p, p)]*w[(q, q)]).abs().sqrt() {
+                    residual = residual.max(g.abs());
+                    continue;
+                }
+                let (c, s, t) = jacobi_rotation(w[(p, p)], w[(q, q)], g);
+                rots.push((p, q, c, s, w[(p, p)]-t*g, w[(q, q)]+t*g));
+            }
+
+            if rots.is_empty() {
+                continue;
+            }
+            rotated = true;
+
+            // A = J'AJ: columns of every row first, then the row pairs, then the exact 2x2 blocks.
+            w.as_mut_slice().par_chunks_mut(n).for_each(|row| {
+                for &(p, q, c, s, _, _) in rots.iter() {
+                    let u = row[p];
+                    let v = row[q];
+                    row[p] = c*u-s*v;
+                    row[q] = s*u+c*v;
+                }
+            });
+
+            let pairs:Vec<(usize, usize)> = rots.iter().map(|x| (x.0, x.1)).collect();
+            row_pairs(w.as_mut_slice(), n, &pairs).into_par_iter().zip(rots.par_iter()).for_each(|((x, y), &(_, _, c, s, _, _))| {
+                rotate(x, y, c, s);
+            });
+            row_pairs(vt.as_mut_slice(), n, &pairs).into_par_iter().zip(rots.par_iter()).for_each(|((x, y), &(_, _, c, s, _, _))| {
+                rotate(x, y, c, s);
+            });
+
+            for &(p, q, _, _, app, aqq) in rots.iter() {
+                w[(p, p)] = app;
+                w[(q, q)] = aqq;
+                w[(p, q)] = 0.0;
+                w[(q, p)] = 0.0;
+            }
+        }
+
+        if rotated {
+            sweeps += 1;
+        }
+        else {
+            converged = true;
+        }
+    }
+
+    let mut order:Vec<usize> = (0..n).collect();
+    order.sort_by(|&p, &q| w[(p, p)].total_cmp(&w[(q, q)]));
+
+    let vals:Vec<f64> = order.iter().map(|&i| w[(i, i)]).collect();
+    let mut v = Matrix::zeros(n, n);
+    for (j, &k) in order.iter().enumerate() {
+        let row = vt.row(k);
+        for i in 0..n {
+            v[(i, j)] = row[i];
+        }
+    }
+
+    return Ok((vals, v, Convergence { iterations: sweeps, residual, converged }));
+}
+
+// Unit vector orthogonal to the first k rows of q, which are orthonormal; used to complete U
+// where a singular value is zero.
+fn orthogonal_complement(q:&Matrix, k:usize) -> Vec<f64> {
+    let n = q.ncol;
+    let mut best = vec![0.0;n];
+    let mut best_norm = -1.0;
+
+    for e in 0..n {
+        let mut x = vec![0.0;n];
+        x[e] = 1.0;
+        for _ in 0..2 {
+            for i in 0..k {
+                let h = dot_product(q.row(i), &x);
+                for j in 0..n {
+                    x[j] -= h*q[(i, j)];
+                }
+            }
+        }
+        let nrm = norm(&x, n);
+        if nrm > best_norm {
+            best_norm = nrm;
+            best = x;
+        }
+        if nrm > 0.5 {
+            break;
+        }
+    }
+
+    for v in best.iter_mut() {
+        *v /= best_norm;
+    }
+    return best;
+}
+
+// A = U S V' by one-sided (Hestenes) Jacobi: columns of A are rotated in pairs until they are
+// mutually orthogonal, |a_p'a_q| <= tol*|a_p||a_q| with tol defaulting to sqrt(n)*eps, which
+// gives singular values to high relative accuracy when A is a well conditioned matrix with
+// badly scaled columns. Returns a thin (U n x r, S r x r, Vt r x m), r = min(n, m), with
+// singular values non-negative and decreasing; max_iter counts sweeps.
+pub fn jacobi_svd(a:&Matrix, options:&IterOptions) -> Result<(Matrix, Matrix, Matrix, Convergence), LinalgError> {
+    check_finite(a.as_slice())?;
+
+    let (n, m) = a.shape();
+    if n < m {
+        let (u, s, vt, conv) = jacobi_svd(&a.transpose(), options)?;
+        return Ok((vt.transpose(), s, u.transpose(), conv));
+    }
+
+    // Columns of A are kept as the rows of w so that every rotation touches contiguous memory.
+    let mut w = a.transpose();
+    let mut vt = Matrix::identity(m);
+    let tol = options.tol_or((n as f64).sqrt()*f64::EPSILON);
+    let max_sweeps = options.max_iter_or(MAX_SWEEPS);
+    let k = m+m%2;
+
+    let mut sweeps = 0;
+    let mut residual:f64 = 0.0;
+    let mut converged = m < 2;
+
+    while !converged && sweeps < max_sweeps {
+        let mut rotated = false;
+        residual = 0.0;
+
+        for r in 0..k-1 {
+            let pairs:Vec<(usize, usize)> = round_robin(k, r).into_iter().filter(|&(_, q)| q < m).collect();
+
+            let rots:Vec<Option<(f64, f64)>> = row_pairs(w.as_mut_slice(), n, &pairs).into_par_iter().map(|(x, y)| {
+                let alpha = dot_product_simd(x, x);
+                let beta = dot_product_simd(y, y);
+                let gamma = dot_product_simd(x, y);
+
+                if gamma == 0.0 || gamma.abs() <= tol*alpha.sqrt()*beta.sqrt() {
+                    return None;
+                }
+                let (c, s, _) = jacobi_rotation(alpha, beta, gamma);
+                rotate(x, y, c, s);
+                return Some((c, s));
+            }).collect();
+
+            for (i, rot) in rots.iter().enumerate() {
+                if rot.is_none() {
+                    let (p, q) = pairs[i];
+                    let g = dot_product_simd(w.row(p), w.row(q)).abs();
+                    residual = residual.max(g);
+                }
+            }
+
+            if rots.iter().all(|x| x.is_none()) {
+                continue;
+            }
+            rotated = true;
+
+            row_pairs(vt.as_mut_slice(), m, &pairs).into_par_iter().zip(rots.par_iter()).for_each(|((x, y), rot)| {
+                if let Some((c, s)) = rot {
+                    rotate(x, y, *c, *s);
+                }
+            });
+        }
+
+        if rotated {
+            sweeps += 1;
+        }
+        else {
+            converged = true;
+        }
+    }
+
+    let sv:Vec<f64> = (0..m).map(|j| norm(w.row(j), n)).collect();
+    let mut order:Vec<usize> = (0..m).collect();
+    order.sort_by(|&p, &q| sv[q].total_cmp(&sv[p]));
+
+    // Rows of ut are the left singular vectors; those of zero singular values are completed
+    // to an orthonormal set.
+    let mut ut = Matrix::zeros(m, n);
+    let mut s = Matrix::zeros(m, m);
+    let mut vt_sorted = Matrix::zeros(m, m);
+
+    for (i, &j) in order.iter().enumerate() {
+        s[(i, i)] = sv[j];
+        vt_sorted.row_mut(i).copy_from_slice(vt.row(j));
+        if sv[j] > 0.0 {
+            for (x, y) in ut.row_mut(i).iter_mut().zip(w.row(j)) {
+                *x = y/sv[j];
+            }
+        }
+        else {
+            let x = orthogonal_complement(&ut, i);
+            ut.row_mut(i).copy_from_slice(&x);
+        }
+    }
+
+    return Ok((ut.transpose(), s, vt_sorted, Convergence { iterations: sweeps, residual, converged }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric_eigen::{symmetric_eigen_with_backend, EigenBackend};
+    use crate::svd::{svd_with_backend, SvdBackend};
+    use crate::qr_decomposition::thin_qr;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    #[test]
+    fn jacobi_eigen_matches_tridiagonal_qr() {
+        let b = random(17, 17, 1);
+        let a = b.clone()+b.transpose();
+        let defaults = IterOptions::default();
+        let (w0, _, _) = symmetric_eigen_with_backend(&a, EigenBackend::TridiagonalQr, &defaults).unwrap();
+        let (w1, v1, conv) = symmetric_eigen_with_backend(&a, EigenBackend::Jacobi, &defaults).unwrap();
+        assert!(conv.converged);
+        for i in 0..17 {
+            assert!((w0[i]-w1[i]).abs() < 1e-12);
+        }
+        assert!(max_abs_diff(&v1.tr_mul(&v1), &Matrix::identity(17)) < 1e-12);
+        assert!(max_abs_diff(&(a*v1.clone()), &(v1*Matrix::from_diag(&w1))) < 1e-12);
+    }
+
+    #[test]
+    fn svd_backends_agree_on_sorted_output() {
+        for &(n, m) in &[(13, 8), (6, 11), (1, 5)] {
+            let a = random(n, m, 2);
+            let r = n.min(m);
+            let defaults = IterOptions::default();
+            let mut values = vec![];
+            for backend in [SvdBackend::GolubKahan, SvdBackend::Jacobi] {
+                let f = svd_with_backend(&a, backend, &defaults).unwrap();
+                assert!(f.convergence.converged);
+                let (u, vt) = (f.u.unwrap(), f.vt.unwrap());
+                assert_eq!((u.shape(), f.s.len(), vt.shape()), ((n, r), r, (r, m)));
+                assert!(f.s.windows(2).all(|p| p[0] >= p[1]) && f.s[r-1] >= 0.0, "{:?} {:?}", backend, f.s);
+                assert!(max_abs_diff(&(u.clone()*Matrix::from_diag(&f.s)*vt.clone()), &a) < 1e-12);
+                assert!(max_abs_diff(&u.tr_mul(&u), &Matrix::identity(r)) < 1e-12);
+                assert!(max_abs_diff(&vt.mul_tr(&vt), &Matrix::identity(r)) < 1e-12);
+                values.push(f.s);
+            }
+            assert!(values[0].iter().zip(values[1].iter()).all(|(x, y)| (x-y).abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn jacobi_svd_keeps_small_singular_values_accurate() {
+        // Orthonormal columns scaled by d: the singular values are d, over 24 orders of magnitude.
+        let (q, _) = thin_qr(&random(10, 4, 3));
+        let d = [1.0, 1e-8, 1e-16, 1e-24];
+        let a = q*Matrix::from_diag(&d);
+        let (_, s, _, _) = jacobi_svd(&a, &IterOptions::default()).unwrap();
+        for i in 0..4 {
+            assert!((s[(i, i)]-d[i]).abs() <= 1e-13*d[i], "{} vs {}", s[(i, i)], d[i]);
+        }
+    }
+}
@@ -0,0 +1,583 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::matrix_view::MatrixView;
+use crate::linalg_error::*;
+use crate::convergence::{IterOptions, Convergence};
+use crate::linear_operator::LinearOperator;
+use crate::complex::Complex;
+use crate::symmetric_eigen::symmetric_eigen;
+use crate::general_eigen::{general_eigen, real_schur, GeneralEigen};
+use crate::qr_decomposition::{thin_qr, column_pivoted_qr};
+use rand::{SeedableRng, rngs::StdRng};
+use rand_distr::{Distribution, Normal};
+
+// Restarts allowed before giving up.
+const MAX_RESTARTS:usize = 300;
+
+// Fixed seed for the starting vector, so that repeated runs give identical results.
+pub(crate) const START_SEED:u64 = 0x5eed;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Which {
+    // Largest eigenvalues; largest in magnitude for nonsymmetric input.
+    #[default]
+    Largest,
+    // Smallest eigenvalues; smallest in magnitude for nonsymmetric input.
+    Smallest,
+    // Closest to the shift, by shift-invert when the operator offers a shifted solver (dense
+    // matrices do, through LU). Otherwise only products with A are used and interior
+    // eigenvalues converge much more slowly than extreme ones.
+    Nearest(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct KrylovOptions {
+    pub which: Which,
+    // Dimension of the Krylov subspace; defaults to max(2k + 1, 20), capped at n.
+    pub ncv: Option<usize>,
+    // max_iter counts restarts. A Ritz pair is accepted once its residual estimate is at most
+    // tol*max(|theta|, eps^(2/3) |H|), tol defaulting to eps; under shift-invert this applies
+    // to the eigenvalues of (A - sigma I)^-1.
+    pub iter: IterOptions,
+}
+
+impl KrylovOptions {
+    pub fn new(which:Which, ncv:Option<usize>, iter:IterOptions) -> Self {
+        Self {
+            which,
+            ncv,
+            iter,
+        }
+    }
+}
+
+// Krylov decomposition AV = VH + v_m h_m,m-1 e_m-1', with the basis vectors as the rows of v
+// and H of size (m+1) x m. After a restart to p vectors the leading p x p block of H is full and
+// row p holds the coupling; the columns added after it are Hessenberg again.
+struct Krylov {
+    v: Matrix,
+    h: Matrix,
+    rng: StdRng,
+}
+
+// Removes the components of w along the rows of basis, which are orthonormal, twice so that
+// the result is orthogonal to working precision, and returns the coefficients.
+pub(crate) fn orthogonalize(basis:MatrixView, w:&mut [f64]) -> Vec<f64> {
+    let (j, n) = basis.shape();
+    let mut h = vec![0.0;j];
+
+    for _ in 0..2 {
+        let c = matrix_vector_multiply(basis, w).unwrap();
+        let p = vector_matrix_multiply(&c, basis).unwrap();
+        for i in 0..n {
+            w[i] -= p[i];
+        }
+        for i in 0..j {
+            h[i] += c[i];
+        }
+    }
+    return h;
+}
+
+// Random unit vector orthogonal to the rows of basis, or zero when they span R^n.
+pub(crate) fn random_orthogonal(basis:MatrixView, rng:&mut StdRng) -> Vec<f64> {
+    let (j, n) = basis.shape();
+    if j >= n {
+        return vec![0.0;n];
+    }
+
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).unwrap();
+    let mut x:Vec<f64> = (0..n).map(|_| normal.sample(rng)).collect();
+    orthogonalize(basis, &mut x);
+
+    let nrm = norm(&x, n);
+    for v in x.iter_mut() {
+        *v /= nrm;
+    }
+    return x;
+}
+
+impl Krylov {
+    fn new(n:usize, m:usize) -> Self {
+        let mut kr = Self {
+            v: Matrix::zeros(m+1, n),
+            h: Matrix::zeros(m+1, m),
+            rng: StdRng::seed_from_u64(START_SEED),
+        };
+        let x = kr.random_orthogonal(0);
+        kr.v.row_mut(0).copy_from_slice(&x);
+        return kr;
+    }
+
+    fn orthogonalize(&self, j:usize, w:&mut [f64]) -> Vec<f64> {
+        return orthogonalize(self.v.view().subview(0..j, 0..self.v.ncol), w);
+    }
+
+    fn random_orthogonal(&mut self, j:usize) -> Vec<f64> {
+        return random_orthogonal(self.v.view().subview(0..j, 0..self.v.ncol), &mut self.rng);
+    }
+
+    // Arnoldi steps from vector p up to m. On breakdown, when Av lies in the current span, the
+    // sequence continues from a fresh random vector with a zero coupling.
+    #[allow(clippy::needless_range_loop)]
+    fn extend(&mut self, op:&dyn Fn(&[f64]) -> Vec<f64>, p:usize, m:usize) {
+        let n = self.v.ncol;
+
+        for j in p..m {
+            let mut w = op(self.v.row(j));
+            let wnorm = norm(&w, n);
+            let h = self.orthogonalize(j+1, &mut w);
+
+            for i in 0..=j {
+                self.h[(i, j)] = h[i];
+            }
+
+            let beta = norm(&w, n);
+            if beta > f64::EPSILON*wnorm && beta > 0.0 {
+                self.h[(j+1, j)] = beta;
+                for v in w.iter_mut() {
+                    *v /= beta;
+                }
+                self.v.row_mut(j+1).copy_from_slice(&w);
+            }
+            else {
+                self.h[(j+1, j)] = 0.0;
+                let x = self.random_orthogonal(j+1);
+                self.v.row_mut(j+1).copy_from_slice(&x);
+            }
+        }
+    }
+
+    // Keeps the span of the orthonormal columns of q (m x p), which must be invariant under
+    // H: V_p = V_m q, H_p = q'H q, coupling row b = h_m,m-1 q(m-1, :), and v_p = v_m.
+    fn restart(&mut self, q:&Matrix) {
+        let (m, p) = q.shape();
+        let n = self.v.ncol;
+
+        let hm = self.h.view().subview(0..m, 0..m);
+        let s = matrix_multiply_view(q.view().t(), matrix_multiply_view(hm, q.view()).unwrap().view()).unwrap();
+        let vp = matrix_multiply_view(q.view().t(), self.v.view().subview(0..m, 0..n)).unwrap();
+        let next = self.v.row(m).to_vec();
+        let beta = self.h[(m, m-1)];
+
+        self.h = Matrix::zeros(m+1, m);
+        self.h.view_mut().subview_mut(0..p, 0..p).copy_from(s.view());
+        for j in 0..p {
+            self.h[(p, j)] = beta*q[(m-1, j)];
+        }
+
+        self.v.view_mut().subview_mut(0..p, 0..n).copy_from(vp.view());
+        self.v.row_mut(p).copy_from_slice(&next);
+    }
+
+    // Ritz vector V_m y.
+    fn ritz_vector(&self, y:&[Complex]) -> Vec<Complex> {
+        let m = y.len();
+        let n = self.v.ncol;
+        let basis = self.v.view().subview(0..m, 0..n);
+
+        let yr:Vec<f64> = y.iter().map(|c| c.re).collect();
+        let xr = vector_matrix_multiply(&yr, basis).unwrap();
+        if y.iter().all(|c| c.im == 0.0) {
+            return xr.iter().map(|&x| Complex::from_real(x)).collect();
+        }
+
+        let yi:Vec<f64> = y.iter().map(|c| c.im).collect();
+        let xi = vector_matrix_multiply(&yi, basis).unwrap();
+        return xr.iter().zip(&xi).map(|(&a, &b)| Complex::new(a, b)).collect();
+    }
+}
+
+// Smaller is more wanted.
+fn rank_key(lambda:Complex, which:Which, symmetric:bool) -> f64 {
+    return match which {
+        Which::Largest => if symmetric { -lambda.re } else { -lambda.abs() },
+        Which::Smallest => if symmetric { lambda.re } else { lambda.abs() },
+        Which::Nearest(sigma) => (lambda-sigma).abs(),
+    };
+}
+
+// Eigenvalues near sigma of A are the largest in magnitude of (A - sigma I)^-1.
+fn shift_invert_key(nu:Complex) -> f64 {
+    return -nu.abs();
+}
+
+// Eigenpairs of the m x m projection, the vectors with unit 2-norm.
+fn projected_eigen(h:&Matrix, symmetric:bool) -> Result<(Vec<Complex>, Vec<Vec<Complex>>), LinalgError> {
+    let m = h.nrow;
+    if symmetric {
+        let (w, y) = symmetric_eigen(h)?;
+        let vecs = (0..m).map(|j| y.column(j).iter().map(|&x| Complex::from_real(x)).collect()).collect();
+        return Ok((w.iter().map(|&x| Complex::from_real(x)).collect(), vecs));
+    }
+
+    let eig = general_eigen(h, false, true)?;
+    return Ok((eig.values, eig.right.unwrap()));
+}
+
+// t = G^H t G and u = u G for the unitary G acting on indices k and k+1 of the m x m row-major
+// complex t and u.
+fn rotate_complex(t:&mut [Complex], u:&mut [Complex], m:usize, k:usize, g:[[Complex;2];2]) {
+    for j in 0..m {
+        let (x, y) = (t[k*m+j], t[(k+1)*m+j]);
+        t[k*m+j] = g[0][0].conj()*x+g[1][0].conj()*y;
+        t[(k+1)*m+j] = g[0][1].conj()*x+g[1][1].conj()*y;
+    }
+    for i in 0..m {
+        for w in [&mut *t, &mut *u] {
+            let (x, y) = (w[i*m+k], w[i*m+k+1]);
+            w[i*m+k] = x*g[0][0]+y*g[1][0];
+            w[i*m+k+1] = x*g[0][1]+y*g[1][1];
+        }
+    }
+}
+
+// Unitary G whose first column is the direction of (x0, x1), or None for a zero vector.
+fn unitary_from(x0:Complex, x1:Complex) -> Option<[[Complex;2];2]> {
+    let r = x0.abs().hypot(x1.abs());
+    if r == 0.0 {
+        return None;
+    }
+    let (c0, c1) = (x0/r, x1/r);
+    return Some([[c0, -c1.conj()], [c1, c0.conj()]]);
+}
+
+// Orthonormal real basis (m x p) of the invariant subspace of h belonging to its p most wanted
+// eigenvalues, a set closed under conjugation. The real Schur form is made complex triangular,
+// the wanted eigenvalues are moved to the top by adjacent swaps, and the leading p Schur
+// vectors are made real again. Unlike a basis of eigenvectors, this stays accurate when
+// eigenvalues cluster.
+fn wanted_schur_basis(h:&Matrix, p:usize, key:&dyn Fn(Complex) -> f64) -> Result<Matrix, LinalgError> {
+    let (tr, z) = real_schur(h)?;
+    let m = h.nrow;
+    let mut t:Vec<Complex> = tr.as_slice().iter().map(|&x| Complex::from_real(x)).collect();
+    let mut u:Vec<Complex> = z.as_slice().iter().map(|&x| Complex::from_real(x)).collect();
+
+    let mut k = 0;
+    while k+1 < m {
+        if tr[(k+1, k)] == 0.0 {
+            k += 1;
+            continue;
+        }
+        // Eigenvector (b, lambda - a) of the 2x2 block [a b; c d].
+        let (a, b, c, d) = (tr[(k, k)], tr[(k, k+1)], tr[(k+1, k)], tr[(k+1, k+1)]);
+        let q = 0.5*(a-d);
+        let lambda = Complex::new(0.5*(a+d), (-(q*q+b*c)).max(0.0).sqrt());
+        if let Some(g) = unitary_from(Complex::from_real(b), lambda-a) {
+            rotate_complex(&mut t, &mut u, m, k, g);
+        }
+        t[k*m+k] = lambda;
+        t[(k+1)*m+k+1] = lambda.conj();
+        t[(k+1)*m+k] = Complex::default();
+        k += 2;
+    }
+
+    // Moves the eigenvalue at position from up to position to by adjacent swaps, each with the
+    // rotation taking the eigenvector (t_j,j+1, t_j+1,j+1 - t_jj) of the 2x2 triangle to e1.
+    let mut bubble = |t:&mut Vec<Complex>, from:usize, to:usize| {
+        for j in (to..from).rev() {
+            let (t11, t12, t22) = (t[j*m+j], t[j*m+j+1], t[(j+1)*m+j+1]);
+            if let Some(g) = unitary_from(t12, t22-t11) {
+                rotate_complex(t, &mut u, m, j, g);
+            }
+            t[j*m+j] = t22;
+            t[(j+1)*m+j+1] = t11;
+            t[(j+1)*m+j] = Complex::default();
+        }
+    };
+
+    // A complex eigenvalue is moved together with its conjugate, so p grows or shrinks by one
+    // rather than split a pair; p stays below m for the restart.
+    let mut i = 0;
+    while i < p {
+        let best = (i..m).min_by(|&x, &y| key(t[x*m+x]).total_cmp(&key(t[y*m+y]))).unwrap();
+        if t[best*m+best].im == 0.0 {
+            bubble(&mut t, best, i);
+            i += 1;
+            continue;
+        }
+        if i+2 >= m {
+            break;
+        }
+        bubble(&mut t, best, i);
+        let target = t[i*m+i].conj();
+        let pair = (i+1..m).min_by(|&x, &y| (t[x*m+x]-target).abs().total_cmp(&(t[y*m+y]-target).abs())).unwrap();
+        bubble(&mut t, pair, i+1);
+        i += 2;
+    }
+    let p = i;
+
+    let mut y = Matrix::zeros(m, 2*p);
+    for i in 0..m {
+        for j in 0..p {
+            y[(i, j)] = u[i*m+j].re;
+            y[(i, p+j)] = u[i*m+j].im;
+        }
+    }
+    let qr = column_pivoted_qr(&y, None)?;
+    return Ok(qr.qr.thin_q().view().subview(0..m, 0..p).to_matrix());
+}
+
+// Wanted Ritz values, their Ritz vectors, and how the restarts ended.
+type RitzPairs = (Vec<Complex>, Vec<Vec<Complex>>, Convergence);
+
+// Krylov-Schur iteration: Arnoldi steps up to m, then a restart onto the invariant subspace of
+// the projection belonging to the p most wanted Ritz values, until the k most wanted converge.
+// Returns the k Ritz pairs of op, most wanted (smallest key) first.
+fn krylov_schur(op:&dyn Fn(&[f64]) -> Vec<f64>, n:usize, k:usize, options:&KrylovOptions, key:&dyn Fn(Complex) -> f64, symmetric:bool) -> Result<RitzPairs, LinalgError> {
+    let m = options.ncv.unwrap_or((2*k+1).max(20)).max(k+1).min(n);
+    let tol = options.iter.tol_or(f64::EPSILON);
+    let max_iter = options.iter.max_iter_or(MAX_RESTARTS);
+    let eps23 = f64::EPSILON.powf(2.0/3.0);
+
+    let mut kr = Krylov::new(n, m);
+    kr.extend(op, 0, m);
+    let mut iter = 0;
+
+    loop {
+        let hm = kr.h.view().subview(0..m, 0..m).to_matrix();
+        check_finite(hm.as_slice())?;
+        let (values, vecs) = projected_eigen(&hm, symmetric)?;
+
+        let mut order:Vec<usize> = (0..m).collect();
+        order.sort_by(|&p, &q| key(values[p]).total_cmp(&key(values[q])));
+
+        let beta = kr.h[(m, m-1)].abs();
+        let hnorm = values.iter().fold(0.0, |acc:f64, v| acc.max(v.abs()));
+        let mut residual:f64 = 0.0;
+        let mut converged = true;
+
+        for &i in order.iter().take(k) {
+            let est = beta*vecs[i][m-1].abs();
+            residual = residual.max(est);
+            if est > tol*values[i].abs().max(eps23*hnorm) {
+                converged = false;
+            }
+        }
+
+        // With m = n the basis spans R^n, so H is similar to A and there is no smaller
+        // space to restart onto; this is always the case for n = 1.
+        if m == n {
+            converged = true;
+        }
+
+        if converged || iter == max_iter {
+            let vals = order.iter().take(k).map(|&i| values[i]).collect();
+            let xs = order.iter().take(k).map(|&i| kr.ritz_vector(&vecs[i])).collect();
+            return Ok((vals, xs, Convergence { iterations: iter, residual, converged }));
+        }
+        iter += 1;
+
+        // Keep half of the unwanted directions as well. Here k < m < n, so 1 <= p < m.
+        let p = k+(m-k)/2;
+
+        let q = if symmetric {
+            let mut y = Matrix::zeros(m, p);
+            for (c, &i) in order.iter().take(p).enumerate() {
+                for r in 0..m {
+                    y[(r, c)] = vecs[i][r].re;
+                }
+            }
+            thin_qr(&y).0
+        }
+        else {
+            wanted_schur_basis(&hm, p, key)?
+        };
+
+        kr.restart(&q);
+        kr.extend(op, q.ncol, m);
+    }
+}
+
+fn check_krylov_input<A:LinearOperator+?Sized>(a:&A, k:usize) -> Result<usize, LinalgError> {
+    let (n, m) = a.shape();
+    check_square(n, m)?;
+    if k == 0 || k > n {
+        return Err(LinalgError::InvalidRank { rank: k, max: n });
+    }
+    return Ok(n);
+}
+
+// Ritz pairs of A, through (A - sigma I)^-1 when a shift is given and the operator can solve
+// with it, with the eigenvalues mapped back by lambda = sigma + 1/nu. A shift that makes
+// A - sigma I singular is an error rather than a silent fallback.
+fn krylov_pairs<A:LinearOperator+?Sized>(a:&A, k:usize, options:&KrylovOptions, shift:Option<f64>, symmetric:bool) -> Result<RitzPairs, LinalgError> {
+    let n = check_krylov_input(a, k)?;
+
+    if let Some(sigma) = shift {
+        if let Some(solve) = a.shift_invert(sigma)? {
+            let (nu, xs, conv) = krylov_schur(&*solve, n, k, options, &shift_invert_key, symmetric)?;
+            let values = nu.iter().map(|&v| Complex::from_real(1.0)/v+sigma).collect();
+            return Ok((values, xs, conv));
+        }
+    }
+
+    let op = |x:&[f64]| a.apply(x);
+    let which = options.which;
+    return krylov_schur(&op, n, k, options, &|v| rank_key(v, which, symmetric), symmetric);
+}
+
+// k eigenpairs of symmetric A by thick-restarted Lanczos with full reorthogonalization, using
+// only products with A unless shift-invert applies. Values come most wanted first, with the
+// orthonormal eigenvectors as the columns of the n x k matrix.
+pub fn lanczos_eigen<A:LinearOperator+?Sized>(a:&A, k:usize, options:&KrylovOptions) -> Result<(Vec<f64>, Matrix, Convergence), LinalgError> {
+    let shift = match options.which {
+        Which::Nearest(sigma) => Some(sigma),
+        _ => None,
+    };
+    let (vals, xs, conv) = krylov_pairs(a, k, options, shift, true)?;
+    let n = a.shape().0;
+
+    let mut v = Matrix::zeros(n, k);
+    for j in 0..k {
+        for i in 0..n {
+            v[(i, j)] = xs[j][i].re;
+        }
+    }
+
+    return Ok((vals.iter().map(|x| x.re).collect(), v, conv));
+}
+
+// k eigenpairs of general A by Krylov-Schur restarted Arnoldi. The smallest in magnitude, and
+// those nearest a shift, use shift-invert when the operator can solve with A - sigma I, and
+// fail with Singular when sigma (zero for the smallest) is an eigenvalue to working precision.
+// Values come most wanted first with unit right eigenvectors; left vectors are not computed.
+pub fn arnoldi_eigen<A:LinearOperator+?Sized>(a:&A, k:usize, options:&KrylovOptions) -> Result<GeneralEigen, LinalgError> {
+    let shift = match options.which {
+        Which::Largest => None,
+        Which::Smallest => Some(0.0),
+        Which::Nearest(sigma) => Some(sigma),
+    };
+    let (values, right, convergence) = krylov_pairs(a, k, options, shift, false)?;
+
+    return Ok(GeneralEigen {
+        values,
+        right: Some(right),
+        left: None,
+        convergence,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sparse_matrix::SparseMatrix;
+    use rand::Rng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn random_symmetric(n:usize, seed:u64) -> Matrix {
+        let a = random(n, n, seed);
+        return &a + &a.transpose();
+    }
+
+    #[test]
+    fn lanczos_matches_dense_eigenvalues() {
+        let a = random_symmetric(60, 1);
+        let (w, _) = symmetric_eigen(&a).unwrap();
+
+        let (vals, v, conv) = lanczos_eigen(&a, 4, &KrylovOptions::default()).unwrap();
+        assert!(conv.converged);
+        for j in 0..4 {
+            assert!((vals[j]-w[59-j]).abs() < 1e-10);
+            let x = v.column(j);
+            let ax = a.apply(&x);
+            let r = ax.iter().zip(x.iter()).map(|(p, q)| (p-vals[j]*q).abs()).fold(0.0, f64::max);
+            assert!(r < 1e-8);
+        }
+
+        let small = KrylovOptions::new(Which::Smallest, None, IterOptions::default());
+        let (vals, _, _) = lanczos_eigen(&a, 3, &small).unwrap();
+        for j in 0..3 {
+            assert!((vals[j]-w[j]).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn shift_invert_finds_interior_eigenvalues() {
+        let a = random_symmetric(50, 2);
+        let (w, _) = symmetric_eigen(&a).unwrap();
+        let sigma = 0.3;
+        let mut nearest = w.clone();
+        nearest.sort_by(|p, q| (p-sigma).abs().total_cmp(&(q-sigma).abs()));
+
+        let options = KrylovOptions::new(Which::Nearest(sigma), None, IterOptions::default());
+        let (vals, _, conv) = lanczos_eigen(&a, 3, &options).unwrap();
+        assert!(conv.converged);
+        for j in 0..3 {
+            assert!((vals[j]-nearest[j]).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn arnoldi_matches_dense_eigenvalues() {
+        let n = 50;
+        let a = random(n, n, 3);
+        let mut w = general_eigen(&a, false, false).unwrap().values;
+        w.sort_by(|p, q| q.abs().total_cmp(&p.abs()));
+
+        let eig = arnoldi_eigen(&a, 4, &KrylovOptions::default()).unwrap();
+        assert!(eig.convergence.converged);
+        let right = eig.right.unwrap();
+        for j in 0..4 {
+            let lambda = eig.values[j];
+            assert!((lambda.abs()-w[j].abs()).abs() < 1e-10);
+            assert!(w.iter().any(|&mu| (mu-lambda).abs() < 1e-10));
+
+            // Real and imaginary parts of Ax - lambda x.
+            let x = &right[j];
+            let re:Vec<f64> = x.iter().map(|c| c.re).collect();
+            let im:Vec<f64> = x.iter().map(|c| c.im).collect();
+            let (are, aim) = (a.apply(&re), a.apply(&im));
+            for i in 0..n {
+                let r = Complex::new(are[i], aim[i])-lambda*x[i];
+                assert!(r.abs() < 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_operator_gives_second_difference_spectrum() {
+        let n:usize = 100;
+        let mut keys = vec![];
+        let mut data = vec![];
+        for i in 0..n {
+            for j in i.saturating_sub(1)..(i+2).min(n) {
+                keys.push(i*n+j);
+                data.push(if i == j { 2.0 } else { -1.0 });
+            }
+        }
+        let a = SparseMatrix::new(n, n, keys, data);
+
+        let (vals, _, _) = lanczos_eigen(&a, 3, &KrylovOptions::default()).unwrap();
+        for (j, v) in vals.iter().enumerate() {
+            let exact = 2.0-2.0*((n-j) as f64*std::f64::consts::PI/(n+1) as f64).cos();
+            assert!((v-exact).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn shift_at_an_eigenvalue_is_singular() {
+        let a = Matrix::from_diag(&[1.0, 2.0, 3.0, 4.0]);
+        let options = KrylovOptions::new(Which::Nearest(2.0), None, IterOptions::default());
+        assert!(matches!(lanczos_eigen(&a, 1, &options), Err(LinalgError::Singular { .. })));
+        assert!(matches!(a.shift_invert(2.0), Err(LinalgError::Singular { .. })));
+    }
+
+    #[test]
+    fn tiny_operators_need_no_restart() {
+        let a = Matrix::new(1, 1, vec![5.0]);
+        let (vals, v, conv) = lanczos_eigen(&a, 1, &KrylovOptions::default()).unwrap();
+        assert!(conv.converged);
+        assert!((vals[0]-5.0).abs() < 1e-14);
+        assert!((v[(0, 0)].abs()-1.0).abs() < 1e-14);
+
+        let b = Matrix::new(2, 2, vec![0.0, 1.0, -1.0, 0.0]);
+        let eig = arnoldi_eigen(&b, 2, &KrylovOptions::default()).unwrap();
+        assert!(eig.values.iter().all(|v| (v.abs()-1.0).abs() < 1e-14 && v.re.abs() < 1e-14));
+    }
+}
@@ -0,0 +1,281 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::convergence::{IterOptions, Convergence};
+use crate::linear_operator::LinearOperator;
+use crate::krylov_eigen::{orthogonalize, random_orthogonal, START_SEED};
+use crate::svd::{svd, Svd, SvdMode};
+use rand::{SeedableRng, rngs::StdRng};
+use std::cmp::min;
+
+// Restarts allowed before giving up.
+const MAX_RESTARTS:usize = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct LanczosSvdOptions {
+    // Number of Lanczos vectors on each side; defaults to max(2k + 1, 20), capped at min(n, m).
+    pub ncv: Option<usize>,
+    // max_iter counts restarts. A triplet is accepted once its residual estimate is at most
+    // max(tol*sigma, eps*sigma_max), tol defaulting to eps.
+    pub iter: IterOptions,
+}
+
+impl LanczosSvdOptions {
+    pub fn new(ncv:Option<usize>, iter:IterOptions) -> Self {
+        Self {
+            ncv,
+            iter,
+        }
+    }
+}
+
+// Golub-Kahan-Lanczos decomposition AQ = PB, A'P = QB' + beta q_m e_m-1', with the left and
+// right vectors as the rows of p (m x n) and q ((m+1) x ncol). B is upper bidiagonal except
+// for column k after a restart to k vectors, which couples the kept triplets to the new ones.
+struct Bidiagonalization {
+    p: Matrix,
+    q: Matrix,
+    b: Matrix,
+    beta: f64,
+    rng: StdRng,
+}
+
+impl Bidiagonalization {
+    fn new(n:usize, ncol:usize, m:usize) -> Self {
+        let mut bd = Self {
+            p: Matrix::zeros(m, n),
+            q: Matrix::zeros(m+1, ncol),
+            b: Matrix::zeros(m, m),
+            beta: 0.0,
+            rng: StdRng::seed_from_u64(START_SEED),
+        };
+        let x = random_orthogonal(bd.q.view().subview(0..0, 0..ncol), &mut bd.rng);
+        bd.q.row_mut(0).copy_from_slice(&x);
+        return bd;
+    }
+
+    // Lanczos steps from vector k up to m with full reorthogonalization of both sides. The
+    // projections of Aq_j on the earlier left vectors fill column j of B; on breakdown the
+    // sequence continues from a random vector with a zero coupling.
+    #[allow(clippy::needless_range_loop)]
+    fn extend<A:LinearOperator+?Sized>(&mut self, a:&A, k:usize, m:usize) {
+        let (n, ncol) = (self.p.ncol, self.q.ncol);
+
+        for j in k..m {
+            let mut w = a.apply(self.q.row(j));
+            let wnorm = norm(&w, n);
+            let h = orthogonalize(self.p.view().subview(0..j, 0..n), &mut w);
+            for i in 0..j {
+                self.b[(i, j)] = h[i];
+            }
+
+            let alpha = norm(&w, n);
+            if alpha > f64::EPSILON*wnorm && alpha > 0.0 {
+                self.b[(j, j)] = alpha;
+                for v in w.iter_mut() {
+                    *v /= alpha;
+                }
+            }
+            else {
+                self.b[(j, j)] = 0.0;
+                w = random_orthogonal(self.p.view().subview(0..j, 0..n), &mut self.rng);
+            }
+            self.p.row_mut(j).copy_from_slice(&w);
+
+            let mut r = a.apply_transpose(self.p.row(j));
+            let rnorm = norm(&r, ncol);
+            orthogonalize(self.q.view().subview(0..j+1, 0..ncol), &mut r);
+
+            // Once j+1 = ncol the right vectors span R^ncol and the remainder is rounding.
+            let beta = norm(&r, ncol);
+            if j+1 < ncol && beta > f64::EPSILON*rnorm && beta > 0.0 {
+                self.beta = beta;
+                for v in r.iter_mut() {
+                    *v /= beta;
+                }
+            }
+            else {
+                self.beta = 0.0;
+                r = random_orthogonal(self.q.view().subview(0..j+1, 0..ncol), &mut self.rng);
+            }
+            self.q.row_mut(j+1).copy_from_slice(&r);
+        }
+    }
+
+    // Keeps the k leading singular triplets of B = U S V': P_k = U_k'P, Q_k = V_k'Q, B_k = S_k,
+    // and q_k = q_m. The coupling column is recomputed by the next extend.
+    #[allow(clippy::needless_range_loop)]
+    fn restart(&mut self, u:&Matrix, s:&[f64], vt:&Matrix, k:usize) {
+        let m = self.b.nrow;
+        let (n, ncol) = (self.p.ncol, self.q.ncol);
+
+        let pk = matrix_multiply_view(u.view().subview(0..m, 0..k).t(), self.p.view()).unwrap();
+        let qk = matrix_multiply_view(vt.view().subview(0..k, 0..m), self.q.view().subview(0..m, 0..ncol)).unwrap();
+        let next = self.q.row(m).to_vec();
+
+        self.p.view_mut().subview_mut(0..k, 0..n).copy_from(pk.view());
+        self.q.view_mut().subview_mut(0..k, 0..ncol).copy_from(qk.view());
+        self.q.row_mut(k).copy_from_slice(&next);
+
+        self.b = Matrix::zeros(m, m);
+        for i in 0..k {
+            self.b[(i, i)] = s[i];
+        }
+    }
+}
+
+// A' as an operator, so the recurrence can always run with the right vectors on the short side.
+struct Transposed<'a, A:?Sized>(&'a A);
+
+impl<A:LinearOperator+?Sized> LinearOperator for Transposed<'_, A> {
+    fn shape(&self) -> (usize, usize) {
+        let (n, m) = self.0.shape();
+        return (m, n);
+    }
+
+    fn apply(&self, x:&[f64]) -> Vec<f64> {
+        return self.0.apply_transpose(x);
+    }
+
+    fn apply_transpose(&self, x:&[f64]) -> Vec<f64> {
+        return self.0.apply(x);
+    }
+}
+
+// Top k singular triplets of A by thick-restarted Golub-Kahan-Lanczos bidiagonalization
+// (Baglama and Reichel), touching A only through products with A and A', so a large
+// SparseMatrix is never densified. Singular values come non-increasing with U n x k and
+// V' k x m. A wide A is handled through A', so the right vectors always live in the smaller
+// dimension and span it completely when ncv reaches min(n, m), in which case the first pass
+// is exact.
+pub fn lanczos_svd<A:LinearOperator+?Sized>(a:&A, k:usize, options:&LanczosSvdOptions) -> Result<Svd, LinalgError> {
+    let (n, ncol) = a.shape();
+    let r = min(n, ncol);
+    if k == 0 || k > r {
+        return Err(LinalgError::InvalidRank { rank: k, max: r });
+    }
+
+    if n < ncol {
+        let f = lanczos_svd_tall(&Transposed(a), k, options)?;
+        let (u, vt) = (f.u.unwrap(), f.vt.unwrap());
+        return Ok(Svd { u: Some(vt.transpose()), s: f.s, vt: Some(u.transpose()), convergence: f.convergence });
+    }
+    return lanczos_svd_tall(a, k, options);
+}
+
+// lanczos_svd for n >= m. The triplets are accurate once their residuals |A'u - sigma v| are,
+// and Av = sigma u holds throughout.
+fn lanczos_svd_tall<A:LinearOperator+?Sized>(a:&A, k:usize, options:&LanczosSvdOptions) -> Result<Svd, LinalgError> {
+    let (n, ncol) = a.shape();
+    let m = options.ncv.unwrap_or((2*k+1).max(20)).max(k+1).min(ncol);
+    let tol = options.iter.tol_or(f64::EPSILON);
+    let max_iter = options.iter.max_iter_or(MAX_RESTARTS);
+
+    let mut bd = Bidiagonalization::new(n, ncol, m);
+    bd.extend(a, 0, m);
+    let mut iter = 0;
+
+    loop {
+        check_finite(bd.b.as_slice())?;
+        let f = svd(&bd.b, SvdMode::Thin)?;
+        let (u, vt) = (f.u.unwrap(), f.vt.unwrap());
+
+        let smax = f.s[0];
+        let mut residual:f64 = 0.0;
+        let mut converged = true;
+
+        for i in 0..k {
+            let est = bd.beta*u[(m-1, i)].abs();
+            residual = residual.max(est);
+            if est > (tol*f.s[i]).max(f64::EPSILON*smax) {
+                converged = false;
+            }
+        }
+
+        // With m = ncol the space is complete (beta = 0) and m = k leaves nothing to restart.
+        if converged || iter == max_iter || m == ncol {
+            let uk = matrix_multiply_view(bd.p.view().t(), u.view().subview(0..m, 0..k)).unwrap();
+            let vk = matrix_multiply_view(vt.view().subview(0..k, 0..m), bd.q.view().subview(0..m, 0..ncol)).unwrap();
+            let convergence = Convergence { iterations: iter, residual, converged };
+            return Ok(Svd { u: Some(uk), s: f.s[0..k].to_vec(), vt: Some(vk), convergence });
+        }
+        iter += 1;
+
+        // Keep the k wanted triplets and half of the unwanted ones; k <= p < m as k < m here.
+        let p = k+(m-k)/2;
+        bd.restart(&u, &f.s, &vt, p);
+        bd.extend(a, p, m);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sparse_matrix::SparseMatrix;
+    use rand::Rng;
+
+    // Random n x m data with about half the entries zero.
+    fn sparse_data(n:usize, m:usize, seed:u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return (0..n*m).map(|_| if rng.gen::<f64>() < 0.5 { 0.0 } else { rng.gen::<f64>()-0.5 }).collect();
+    }
+
+    // Singular values against the dense svd, and both residuals of every returned triplet.
+    fn check<A:LinearOperator>(a:&A, dense:&Matrix, k:usize) {
+        let f = lanczos_svd(a, k, &LanczosSvdOptions::default()).unwrap();
+        let s = svd(dense, SvdMode::ValuesOnly).unwrap().s;
+        let tol = 1e-10*s[0].max(1.0);
+        let (n, m) = dense.shape();
+
+        assert!(f.convergence.converged, "{}x{} k={}: {:?}", n, m, k, f.convergence);
+        let (u, vt) = (f.u.unwrap(), f.vt.unwrap());
+        assert_eq!(u.shape(), (n, k));
+        assert_eq!(vt.shape(), (k, m));
+
+        for i in 0..k {
+            assert!((f.s[i]-s[i]).abs() <= tol, "{}x{} k={}: {:?} vs {:?}", n, m, k, f.s, s);
+            let (ui, vi) = (u.column(i), vt.row(i).to_vec());
+            let av = a.apply(&vi);
+            let atu = a.apply_transpose(&ui);
+            for j in 0..n {
+                assert!((av[j]-f.s[i]*ui[j]).abs() <= tol);
+            }
+            for j in 0..m {
+                assert!((atu[j]-f.s[i]*vi[j]).abs() <= tol);
+            }
+        }
+    }
+
+    fn check_shape(n:usize, m:usize, k:usize, seed:u64) {
+        let data = sparse_data(n, m, seed);
+        let dense = Matrix::new(n, m, data.clone());
+        let sparse = SparseMatrix::create(n, m, &data);
+        check(&dense, &dense, k);
+        check(&sparse, &dense, k);
+    }
+
+    #[test]
+    fn lanczos_svd_tall() {
+        check_shape(120, 40, 5, 1);
+    }
+
+    #[test]
+    fn lanczos_svd_wide() {
+        check_shape(40, 120, 5, 2);
+    }
+
+    #[test]
+    fn lanczos_svd_full_rank_request() {
+        check_shape(30, 8, 8, 3);
+        check_shape(8, 30, 8, 4);
+        check_shape(3, 10, 3, 5);
+        check_shape(20, 100, 20, 6);
+    }
+
+    #[test]
+    fn lanczos_svd_single_row_and_column() {
+        check_shape(1, 5, 1, 7);
+        check_shape(5, 1, 1, 8);
+    }
+}
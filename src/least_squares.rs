@@ -0,0 +1,192 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::qr_decomposition::HouseholderQr;
+use crate::solve_linear::backward_sub;
+use crate::svd::golub_reisch_svd;
+
+#[derive(Clone, Debug)]
+pub struct LstsqResult {
+    // m x k minimizer of ||AX - B||, minimum norm when A is rank deficient.
+    pub x: Matrix,
+    // ||b_j - A x_j||^2 for every right-hand side.
+    pub residuals: Vec<f64>,
+    pub rank: usize,
+    // Singular values of A in decreasing order.
+    pub singular_values: Vec<f64>,
+}
+
+// X = V S^+ U' C, dropping singular values at or below cutoff.
+fn svd_solve(u:&Matrix, s:&Matrix, vt:&Matrix, c:&Matrix, cutoff:f64) -> Matrix {
+    let mut w = u.tr_mul(c);
+    for i in 0..w.nrow {
+        let si = s[(i, i)];
+        let h = if si.abs() > cutoff { 1.0/si } else { 0.0 };
+        for v in w.row_mut(i) {
+            *v *= h;
+        }
+    }
+    return vt.tr_mul(&w);
+}
+
+fn sorted_abs_diag(s:&Matrix) -> Vec<f64> {
+    let mut out:Vec<f64> = s.diag().iter().map(|x| x.abs()).collect();
+    out.sort_by(|p, q| q.total_cmp(p));
+    return out;
+}
+
+// Least-squares solution of AX = B. Tall systems go through Householder QR, and the SVD of the
+// small m x m triangle R both gives the singular values of A and, when R turns out to be
+// rank deficient, the minimum norm solution. Wide systems use the SVD of A directly.
+// Singular values at or below rcond*s_max count as zero; rcond defaults to max(n, m)*eps.
+pub fn lstsq(a:&Matrix, b:&Matrix, rcond:Option<f64>) -> Result<LstsqResult, LinalgError> {
+    let (n, m) = a.shape();
+    if b.nrow != n {
+        return Err(LinalgError::DimensionMismatch { expected: (n, b.ncol), found: b.shape() });
+    }
+    check_finite(a.as_slice())?;
+    check_finite(b.as_slice())?;
+
+    let rcond = rcond.unwrap_or((n.max(m) as f64)*f64::EPSILON);
+    let k = b.ncol;
+
+    if n == 0 || m == 0 {
+        let residuals = (0..k).map(|j| b.column(j).iter().map(|v| v*v).sum()).collect();
+        return Ok(LstsqResult {
+            x: Matrix::zeros(m, k),
+            residuals,
+            rank: 0,
+            singular_values: vec![],
+        });
+    }
+
+    let (x, singular_values, rank) = if n >= m {
+        let qr = HouseholderQr::new(a);
+        let mut qtb = b.clone();
+        qr.apply_qt(&mut qtb);
+        let qtb = qtb.view().subview(0..m, 0..k).to_matrix();
+        let r1 = qr.r();
+
+        let (u, s, vt) = golub_reisch_svd(&r1)?;
+        let sv = sorted_abs_diag(&s);
+        let cutoff = rcond*sv[0];
+        let rank = sv.iter().filter(|&&x| x > cutoff).count();
+
+        let x = if rank == m {
+            let mut x = Matrix::zeros(m, k);
+            for j in 0..k {
+                let xj = backward_sub(&r1, &qtb.column(j))?;
+                for i in 0..m {
+                    x[(i, j)] = xj[i];
+                }
+            }
+            x
+        }
+        else {
+            svd_solve(&u, &s, &vt, &qtb, cutoff)
+        };
+
+        (x, sv, rank)
+    }
+    else {
+        let (u, s, vt) = golub_reisch_svd(a)?;
+        let sv = sorted_abs_diag(&s);
+        let cutoff = rcond*sv[0];
+        let rank = sv.iter().filter(|&&x| x > cutoff).count();
+
+        (svd_solve(&u, &s, &vt, b, cutoff), sv, rank)
+    };
+
+    let r = &(a * &x) - b;
+    let mut residuals = vec![0.0;k];
+    for i in 0..n {
+        for j in 0..k {
+            residuals[j] += r[(i, j)]*r[(i, j)];
+        }
+    }
+
+    return Ok(LstsqResult {
+        x,
+        residuals,
+        rank,
+        singular_values,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lu_decomposition::LuFactorization;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    #[test]
+    fn full_rank_tall_matches_normal_equations() {
+        let a = random(30, 6, 1);
+        let b = random(30, 2, 2);
+        let out = lstsq(&a, &b, None).unwrap();
+        assert_eq!(out.rank, 6);
+
+        let x = LuFactorization::new(&a.tr_mul(&a)).unwrap().solve_matrix(&a.tr_mul(&b)).unwrap();
+        assert!(max_abs_diff(&out.x, &x) < 1e-10);
+
+        let r = &(&a * &out.x) - &b;
+        for j in 0..2 {
+            let rj:f64 = r.column(j).iter().map(|v| v*v).sum();
+            assert!((out.residuals[j]-rj).abs() < 1e-10);
+        }
+        assert!(out.singular_values.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn rank_deficient_gives_minimum_norm_solution() {
+        // The last column repeats the first, so the minimum norm solution splits their weight.
+        let c = random(20, 2, 3);
+        let mut a = Matrix::zeros(20, 3);
+        for i in 0..20 {
+            a[(i, 0)] = c[(i, 0)];
+            a[(i, 1)] = c[(i, 1)];
+            a[(i, 2)] = c[(i, 0)];
+        }
+        let b = random(20, 1, 4);
+        let out = lstsq(&a, &b, None).unwrap();
+        assert_eq!(out.rank, 2);
+        assert!((out.x[(0, 0)]-out.x[(2, 0)]).abs() < 1e-10);
+
+        let reduced = lstsq(&c, &b, None).unwrap();
+        assert!((out.x[(0, 0)]+out.x[(2, 0)]-reduced.x[(0, 0)]).abs() < 1e-10);
+        assert!((out.x[(1, 0)]-reduced.x[(1, 0)]).abs() < 1e-10);
+        assert!((out.residuals[0]-reduced.residuals[0]).abs() < 1e-10);
+    }
+
+    #[test]
+    fn wide_system_is_solved_with_minimum_norm() {
+        let a = random(4, 9, 5);
+        let b = random(4, 1, 6);
+        let out = lstsq(&a, &b, None).unwrap();
+        assert_eq!(out.rank, 4);
+        assert!(out.residuals[0] < 1e-12);
+
+        // Minimum norm solution A'(AA')^-1 b.
+        let y = LuFactorization::new(&a.mul_tr(&a)).unwrap().solve_matrix(&b).unwrap();
+        assert!(max_abs_diff(&out.x, &a.tr_mul(&y)) < 1e-10);
+    }
+
+    #[test]
+    fn empty_system_returns_zero_solution() {
+        let b = Matrix::new(2, 1, vec![3.0, 4.0]);
+        let out = lstsq(&Matrix::zeros(2, 0), &b, None).unwrap();
+        assert_eq!(out.x.shape(), (0, 1));
+        assert_eq!(out.residuals, vec![25.0]);
+        assert_eq!(out.rank, 0);
+    }
+}
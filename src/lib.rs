@@ -0,0 +1,74 @@
+#![feature(portable_simd)]
+pub mod matrix_utils;
+pub mod complex;
+pub mod gemm;
+pub mod dense_matrix;
+pub mod matrix_view;
+pub mod linalg_error;
+pub mod convergence;
+pub mod orthogonal_matrices;
+pub mod row_echelon;
+pub mod reduced_row_echelon;
+pub mod lu_decomposition;
+pub mod cholesky;
+pub mod solve_linear;
+pub mod linear_regression_gd;
+pub mod matrix_inverse;
+pub mod qr_decomposition;
+pub mod least_squares;
+pub mod eigenvalues;
+pub mod symmetric_eigen;
+pub mod jacobi;
+pub mod general_eigen;
+pub mod sparse_matrix;
+pub mod linear_operator;
+pub mod krylov_eigen;
+pub mod krylov_svd;
+pub mod svd;
+pub mod matrix_functions;
+
+pub use dense_matrix::Matrix;
+pub use matrix_view::{MatrixView, MatrixViewMut};
+pub use linalg_error::LinalgError;
+pub use complex::Complex;
+pub use convergence::{IterOptions, Convergence};
+pub use lu_decomposition::{LuFactorization, LuOptions, Pivoting};
+pub use cholesky::{Cholesky, Ldlt};
+pub use sparse_matrix::SparseMatrix;
+pub use linear_operator::LinearOperator;
+pub use linear_regression_gd::LinearRegression;
+
+pub mod prelude {
+    pub use crate::dense_matrix::Matrix;
+    pub use crate::matrix_view::{MatrixView, MatrixViewMut};
+    pub use crate::linalg_error::LinalgError;
+    pub use crate::complex::Complex;
+    pub use crate::convergence::{IterOptions, Convergence};
+    pub use crate::sparse_matrix::SparseMatrix;
+    pub use crate::linear_operator::LinearOperator;
+    pub use crate::linear_regression_gd::LinearRegression;
+    pub use crate::lu_decomposition::{lu_decomposition, LuFactorization, LuOptions, Pivoting};
+    pub use crate::cholesky::{cholesky, Cholesky, Ldlt};
+    pub use crate::qr_decomposition::{givens_rotation_qr, householder_reflection_qr, column_pivoted_qr, PivotedQr};
+    pub use crate::qr_decomposition::{thin_qr, HouseholderQr};
+    pub use crate::solve_linear::{solve, solve_with_diagnostics, solve_refined, forward_sub, backward_sub};
+    pub use crate::solve_linear::{SolveDiagnostics, RefineOptions, RefinementReport, Accumulation};
+    pub use crate::least_squares::{lstsq, LstsqResult};
+    pub use crate::matrix_inverse::{inverse, pinv};
+    pub use crate::eigenvalues::{eigenvalues, eigenvectors, eigenvalues_with_options, eigenvectors_with_options};
+    pub use crate::symmetric_eigen::{symmetric_eigen, symmetric_eigenvalues};
+    pub use crate::symmetric_eigen::{symmetric_eigen_with_options, symmetric_eigenvalues_with_options};
+    pub use crate::symmetric_eigen::{symmetric_eigen_with_backend, EigenBackend};
+    pub use crate::symmetric_eigen::{generalized_eigh, generalized_eigh_with_options};
+    pub use crate::jacobi::{jacobi_eigen, jacobi_svd};
+    pub use crate::krylov_eigen::{lanczos_eigen, arnoldi_eigen, KrylovOptions, Which};
+    pub use crate::krylov_svd::{lanczos_svd, LanczosSvdOptions};
+    pub use crate::general_eigen::{general_eigen, general_eigenvalues, hessenberg, real_schur, GeneralEigen};
+    pub use crate::general_eigen::{general_eigen_with_options, real_schur_with_options};
+    pub use crate::svd::{golub_reisch_svd, golub_reisch_svd_with_options, randomized_svd};
+    pub use crate::svd::{svd_with_backend, SvdBackend};
+    pub use crate::svd::{svd, svd_with_options, Svd, SvdMode};
+    pub use crate::svd::RandomizedSvdOptions;
+    pub use crate::svd::{matrix_rank, null_space, orth, spectral_norm, nuclear_norm};
+    pub use crate::matrix_functions::{expm, sqrtm, logm, powm};
+}
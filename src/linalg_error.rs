@@ -0,0 +1,67 @@
+#![allow(clippy::needless_return)]
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinalgError {
+    DimensionMismatch { expected: (usize, usize), found: (usize, usize) },
+    Singular { index: usize },
+    NotConverged { iterations: usize },
+    NotSquare { nrow: usize, ncol: usize },
+    NotPositiveDefinite { index: usize },
+    ComplexEigenvalues,
+    NegativeEigenvalue { index: usize },
+    InvalidRank { rank: usize, max: usize },
+    NonFinite,
+}
+
+impl fmt::Display for LinalgError {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinalgError::DimensionMismatch { expected, found } => {
+                write!(f, "dimension mismatch: expected {}x{}, found {}x{}", expected.0, expected.1, found.0, found.1)
+            }
+            LinalgError::Singular { index } => {
+                write!(f, "matrix is singular: zero pivot at index {}", index)
+            }
+            LinalgError::NotConverged { iterations } => {
+                write!(f, "did not converge after {} iterations", iterations)
+            }
+            LinalgError::NotSquare { nrow, ncol } => {
+                write!(f, "expected a square matrix, found {}x{}", nrow, ncol)
+            }
+            LinalgError::NotPositiveDefinite { index } => {
+                write!(f, "matrix is not positive definite: non-positive pivot at index {}", index)
+            }
+            LinalgError::ComplexEigenvalues => {
+                write!(f, "matrix has complex eigenvalues; use general_eigen")
+            }
+            LinalgError::NegativeEigenvalue { index } => {
+                write!(f, "matrix has a negative real eigenvalue at index {}; no real principal function exists", index)
+            }
+            LinalgError::InvalidRank { rank, max } => {
+                write!(f, "requested rank {} is outside 1..={}", rank, max)
+            }
+            LinalgError::NonFinite => {
+                write!(f, "input contains NaN or infinite values")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LinalgError {}
+
+pub fn check_square(nrow:usize, ncol:usize) -> Result<(), LinalgError> {
+    if nrow != ncol {
+        return Err(LinalgError::NotSquare { nrow, ncol });
+    }
+    return Ok(());
+}
+
+pub fn check_finite(a:&[f64]) -> Result<(), LinalgError> {
+    for x in a {
+        if !x.is_finite() {
+            return Err(LinalgError::NonFinite);
+        }
+    }
+    return Ok(());
+}
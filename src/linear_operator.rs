@@ -0,0 +1,107 @@
+#![allow(clippy::needless_return)]
+use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::sparse_matrix::{SparseMatrix, mul_vec, tr_mul_vec};
+use crate::lu_decomposition::LuFactorization;
+use crate::linalg_error::*;
+
+// x = (A - sigma I)^-1 b
+pub type ShiftedSolve<'a> = Box<dyn Fn(&[f64]) -> Vec<f64> + 'a>;
+
+// Anything that can form Ax and A'x. The Krylov solvers only touch their input through this,
+// so dense and sparse matrices, or matrix-free operators, are handled alike. Callers check the
+// length of x against shape() before applying.
+pub trait LinearOperator {
+    fn shape(&self) -> (usize, usize);
+
+    fn apply(&self, x:&[f64]) -> Vec<f64>;
+
+    fn apply_transpose(&self, x:&[f64]) -> Vec<f64>;
+
+    // AX for a block of vectors, the columns of x. The default applies A column by column.
+    fn apply_matrix(&self, x:&Matrix) -> Matrix {
+        let n = self.shape().0;
+        let mut out = Matrix::zeros(n, x.ncol);
+        for j in 0..x.ncol {
+            let y = self.apply(&x.column(j));
+            for i in 0..n {
+                out[(i, j)] = y[i];
+            }
+        }
+        return out;
+    }
+
+    // A'X for the columns of x.
+    fn apply_transpose_matrix(&self, x:&Matrix) -> Matrix {
+        let m = self.shape().1;
+        let mut out = Matrix::zeros(m, x.ncol);
+        for j in 0..x.ncol {
+            let y = self.apply_transpose(&x.column(j));
+            for i in 0..m {
+                out[(i, j)] = y[i];
+            }
+        }
+        return out;
+    }
+
+    // Solver for (A - sigma I)x = b, for operators that can factor the shifted matrix, or None
+    // when they cannot. The eigensolvers use it to reach eigenvalues near sigma by shift-invert.
+    fn shift_invert(&self, _sigma:f64) -> Result<Option<ShiftedSolve<'_>>, LinalgError> {
+        return Ok(None);
+    }
+}
+
+impl LinearOperator for Matrix {
+    fn shape(&self) -> (usize, usize) {
+        return (self.nrow, self.ncol);
+    }
+
+    fn apply(&self, x:&[f64]) -> Vec<f64> {
+        return matrix_vector_multiply(self.view(), x).unwrap();
+    }
+
+    fn apply_transpose(&self, x:&[f64]) -> Vec<f64> {
+        return vector_matrix_multiply(x, self.view()).unwrap();
+    }
+
+    fn apply_matrix(&self, x:&Matrix) -> Matrix {
+        return self*x;
+    }
+
+    fn apply_transpose_matrix(&self, x:&Matrix) -> Matrix {
+        return self.tr_mul(x);
+    }
+
+    // Singular when A - sigma I is singular to working precision, since sigma is then an
+    // eigenvalue and the shifted operator does not exist. The factors are checked here, so the
+    // solve can only fail on overflow, which comes back as NaN for the caller's finiteness check.
+    fn shift_invert(&self, sigma:f64) -> Result<Option<ShiftedSolve<'_>>, LinalgError> {
+        let n = self.nrow;
+        check_square(n, self.ncol)?;
+
+        let mut b = self.clone();
+        for i in 0..n {
+            b[(i, i)] -= sigma;
+        }
+
+        let lu = LuFactorization::new(&b)?;
+        if lu.rank() < n {
+            return Err(LinalgError::Singular { index: lu.rank() });
+        }
+        return Ok(Some(Box::new(move |x| lu.solve(x).unwrap_or_else(|_| vec![f64::NAN;n]))));
+    }
+}
+
+impl LinearOperator for SparseMatrix {
+    fn shape(&self) -> (usize, usize) {
+        return (self.nrow, self.ncol);
+    }
+
+    fn apply(&self, x:&[f64]) -> Vec<f64> {
+        return mul_vec(self, x).unwrap();
+    }
+
+    fn apply_transpose(&self, x:&[f64]) -> Vec<f64> {
+        return tr_mul_vec(self, x).unwrap();
+    }
+}
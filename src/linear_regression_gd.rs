@@ -1,4 +1,4 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::needless_return)]
 use crate::matrix_utils::*;
 use std::cmp::min;
 use rand::Rng;
@@ -7,10 +7,12 @@ use std::thread;
 use std::sync::{Arc, Mutex};
 
 pub fn predict(data: &[f64], weights: &[f64], bias: f64) -> f64 {
-    let sum: f64 = dot_product_simd(&weights, &data);
+    let sum: f64 = dot_product_simd(weights, data);
     return sum + bias;
 }
 
+type Batch = (Arc<Vec<f64>>, Arc<Vec<f64>>);
+
 #[derive(Clone)]
 pub struct LinearRegression {
     weights: Arc<Mutex<Vec<f64>>>,
@@ -23,6 +25,7 @@ pub struct LinearRegression {
 }
 
 impl LinearRegression {
+    #[allow(clippy::needless_range_loop)]
     pub fn new(
         &n: &usize,
         &num_epochs:&usize, 
@@ -50,9 +53,9 @@ impl LinearRegression {
 }
 
 impl LinearRegression {
-    pub fn predict(&self, data:&Vec<f64>) -> f64 {
+    pub fn predict(&self, data:&[f64]) -> f64 {
         return predict(
-            &data, 
+            data, 
             &self.weights.lock().unwrap(), 
             self.bias
         );
@@ -71,7 +74,7 @@ impl LinearRegression {
         let mut errors:Vec<f64> = vec![0.0;n];
 
         let mut handles = vec![];
-        let q = (n as f64/4.0).ceil() as usize;
+        let q = ((n as f64/4.0).ceil() as usize).max(1);
 
         for i in (0..n).step_by(q) {
             let d = Arc::clone(&data);
@@ -145,14 +148,14 @@ impl LinearRegression {
     pub fn get_weights_gradient(
         &self, 
         data:Arc<Vec<f64>>, 
-        errors:&Vec<f64>,
+        errors:&[f64],
         n:usize,
         m:usize,
     ) -> Vec<f64> {
 
         let mut gradients:Vec<f64> = vec![0.0;m];
         let w = self.weights.lock().unwrap();
-        let res = matrix_multiply_simd(&errors, &data, 1, n, m);
+        let res = matrix_multiply_simd(errors, &data, 1, n, m).unwrap();
         
         for i in 0..m {
             let sum = 2.0*res[i];
@@ -173,6 +176,7 @@ impl LinearRegression {
 }
 
 impl LinearRegression {
+    #[allow(clippy::needless_range_loop)]
     pub fn gradient_descent(
         &mut self, 
         data:Arc<Vec<f64>>, 
@@ -223,17 +227,18 @@ impl LinearRegression {
 }
 
 impl LinearRegression {
+    #[allow(clippy::needless_range_loop)]
     pub fn train(
         &mut self, 
-        data:&Vec<f64>, 
-        labels:&Vec<f64>,
+        data:&[f64], 
+        labels:&[f64],
         n:usize,
         m:usize,
     ) {
-        let d = Arc::new(data.clone());
-        let u = Arc::new(labels.clone());
+        let d = Arc::new(data.to_vec());
+        let u = Arc::new(labels.to_vec());
 
-        let mut batched_data:Vec<(Arc<Vec<f64>>, Arc<Vec<f64>>)> = Vec::new();
+        let mut batched_data:Vec<Batch> = Vec::new();
 
         for i in (0..n).step_by(self.batch_size) {
             let x = &d[i*m..min((i+self.batch_size)*m, n*m)];
@@ -255,29 +260,3 @@ impl LinearRegression {
         }
     }
 }
-
-pub fn run() {
-    let n = 1000;
-    let m = 3000;
-
-    let mut lr = LinearRegression::new(&m, &1000, &1024, &0.0, &0.001, &0.005);
-    let mut data:Vec<f64> = vec![0.0;n*m];
-    let mut labels:Vec<f64> = vec![0.0;n];
-
-    for i in 0..n {
-        for j in 0..m {
-            data[i*m + j] = rand::thread_rng().sample(StandardNormal);
-        }
-        labels[i] = rand::thread_rng().sample(StandardNormal);
-    }
-
-    lr.train(&data, &labels, n, m);
-
-    let mut query:Vec<f64> = vec![0.0;m];
-    for j in 0..m {
-        query[j] = rand::thread_rng().sample(StandardNormal);
-    }
-
-    let preds = lr.predict(&data);
-    println!("{:?}", preds);
-}
\ No newline at end of file
@@ -1,75 +1,578 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::needless_return)]
 use crate::matrix_utils::*;
-use crate::row_echelon::swap_rows;
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::row_echelon::{swap_rows, swap_cols};
 use crate::row_echelon::reduce_row;
-use std::f64::MIN;
-use rand_distr::{Distribution, Normal};
-use rand::thread_rng;
+use rayon::prelude::*;
+use std::cmp::min;
 
-pub fn lu_decomposition(a:&[f64], n:usize, m:usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
-    let mut u:Vec<f64> = a.to_vec();
-    let mut l:Vec<f64> = vec![0.0;n*n];
-    let mut eye:Vec<f64> = identity(n);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Pivoting {
+    // Largest magnitude in the current column; rows only.
+    #[default]
+    Partial,
+    // Alternates column and row searches until the entry is largest in both.
+    Rook,
+    // Largest magnitude in the whole trailing block.
+    Complete,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LuOptions {
+    pub pivoting: Pivoting,
+    // Pivots with magnitude at or below this are treated as zero. When None it defaults to
+    // max(n, m)*eps*max|a_ij|.
+    pub tol: Option<f64>,
+}
+
+impl Default for LuOptions {
+    fn default() -> Self {
+        Self {
+            pivoting: Pivoting::Partial,
+            tol: None,
+        }
+    }
+}
+
+impl LuOptions {
+    pub fn new(pivoting:Pivoting, tol:Option<f64>) -> Self {
+        Self {
+            pivoting,
+            tol,
+        }
+    }
+}
+
+// PAQ = LU stored compactly: the strict lower triangle of `lu` holds the multipliers of the
+// unit lower triangular L and the upper triangle holds U. Entry (i, j) of PAQ is
+// A[perm[i], col_perm[j]]; col_perm is the identity under partial pivoting.
+#[derive(Clone, Debug)]
+pub struct LuFactorization {
+    lu: Matrix,
+    perm: Vec<usize>,
+    col_perm: Vec<usize>,
+    sign: f64,
+    tol: f64,
+    rank: usize,
+    anorm1: f64,
+}
+
+fn argmax_abs(vals:impl Iterator<Item = (usize, f64)>) -> (usize, f64) {
+    let mut best = (0, -1.0);
+    for (i, x) in vals {
+        if x.abs() > best.1 {
+            best = (i, x.abs());
+        }
+    }
+    return best;
+}
+
+// Pivot position and magnitude for step j.
+fn find_pivot(lu:&Matrix, j:usize, pivoting:Pivoting) -> (usize, usize, f64) {
+    let (n, m) = lu.shape();
+
+    match pivoting {
+        Pivoting::Partial => {
+            let (r, v) = argmax_abs((j..n).map(|i| (i, lu[(i, j)])));
+            return (r, j, v);
+        }
+        Pivoting::Rook => {
+            let mut c = j;
+            let (mut r, mut best) = argmax_abs((j..n).map(|i| (i, lu[(i, c)])));
+
+            loop {
+                let (c1, v) = argmax_abs((j..m).map(|k| (k, lu[(r, k)])));
+                if v <= best {
+                    break;
+                }
+                c = c1;
+                best = v;
+
+                let (r1, v) = argmax_abs((j..n).map(|i| (i, lu[(i, c)])));
+                if v <= best {
+                    break;
+                }
+                r = r1;
+                best = v;
+            }
+
+            return (r, c, best);
+        }
+        Pivoting::Complete => {
+            let mut best = (j, j, -1.0);
+            for i in j..n {
+                let (c, v) = argmax_abs((j..m).map(|k| (k, lu[(i, k)])));
+                if v > best.2 {
+                    best = (i, c, v);
+                }
+            }
+            return best;
+        }
+    }
+}
+
+impl LuFactorization {
+    pub fn new(a:&Matrix) -> Result<Self, LinalgError> {
+        return LuFactorization::with_options(a, &LuOptions::default());
+    }
+
+    pub fn with_options(a:&Matrix, options:&LuOptions) -> Result<Self, LinalgError> {
+        check_finite(a.as_slice())?;
+
+        let (n, m) = a.shape();
+        let mut lu = a.clone();
+        let mut perm:Vec<usize> = (0..n).collect();
+        let mut col_perm:Vec<usize> = (0..m).collect();
+        let mut sign = 1.0;
+        let mut rank = 0;
+
+        let tol = match options.tol {
+            Some(t) => t.max(0.0),
+            None => {
+                let amax = a.as_slice().iter().fold(0.0, |acc:f64, x| acc.max(x.abs()));
+                (n.max(m) as f64)*f64::EPSILON*amax
+            }
+        };
+
+        for j in 0..min(n, m) {
+            let (r, c, v) = find_pivot(&lu, j, options.pivoting);
+
+            if r != j {
+                swap_rows(lu.as_mut_slice(), m, j, r);
+                perm.swap(j, r);
+                sign = -sign;
+            }
+
+            if c != j {
+                swap_cols(lu.as_mut_slice(), n, m, j, c);
+                col_perm.swap(j, c);
+                sign = -sign;
+            }
+
+            // A negligible pivot column is dropped rather than divided through, so the
+            // factors stay finite and the deficiency shows up in the rank.
+            if v <= tol {
+                for i in j+1..n {
+                    lu[(i, j)] = 0.0;
+                }
+                continue;
+            }
+
+            rank += 1;
+
+            let pivot = lu[(j, j)];
+            let (top, bottom) = lu.as_mut_slice().split_at_mut((j+1)*m);
+            let pivot_row = &top[j*m+j+1..(j+1)*m];
+
+            bottom.par_chunks_mut(m).for_each(|row| {
+                if row[j] != 0.0 {
+                    let h = row[j]/pivot;
+                    mul_sub_const(pivot_row, &mut row[j+1..m], h, m-j-1);
+                    row[j] = h;
+                }
+            });
+        }
+
+        return Ok(Self {
+            lu,
+            perm,
+            col_perm,
+            sign,
+            tol,
+            rank,
+            anorm1: a.norm1(),
+        });
+    }
+
+    // Number of pivots whose magnitude exceeded the singularity tolerance.
+    pub fn rank(&self) -> usize {
+        return self.rank;
+    }
+
+    pub fn tol(&self) -> f64 {
+        return self.tol;
+    }
+
+    pub fn col_pivots(&self) -> &[usize] {
+        return &self.col_perm;
+    }
+
+    pub fn q(&self) -> Matrix {
+        let m = self.lu.ncol;
+        let mut q = Matrix::zeros(m, m);
+        for j in 0..m {
+            q[(self.col_perm[j], j)] = 1.0;
+        }
+        return q;
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        return self.lu.shape();
+    }
+
+    // Compact L\U storage.
+    pub fn lu(&self) -> &Matrix {
+        return &self.lu;
+    }
+
+    pub fn pivots(&self) -> &[usize] {
+        return &self.perm;
+    }
+
+    pub fn p(&self) -> Matrix {
+        let n = self.lu.nrow;
+        let mut p = Matrix::zeros(n, n);
+        for i in 0..n {
+            p[(i, self.perm[i])] = 1.0;
+        }
+        return p;
+    }
+
+    // Unit lower triangular n x n factor.
+    pub fn l(&self) -> Matrix {
+        let (n, m) = self.lu.shape();
+        let mut l = Matrix::identity(n);
+        for i in 0..n {
+            for j in 0..min(i, m) {
+                l[(i, j)] = self.lu[(i, j)];
+            }
+        }
+        return l;
+    }
+
+    // Upper triangular n x m factor.
+    pub fn u(&self) -> Matrix {
+        let (n, m) = self.lu.shape();
+        let mut u = Matrix::zeros(n, m);
+        for i in 0..n {
+            for j in i..m {
+                u[(i, j)] = self.lu[(i, j)];
+            }
+        }
+        return u;
+    }
+
+    fn check_solvable(&self, nrow:usize, ncol:usize) -> Result<(), LinalgError> {
+        let n = self.lu.nrow;
+        check_square(n, self.lu.ncol)?;
+        if nrow != n {
+            return Err(LinalgError::DimensionMismatch { expected: (n, ncol), found: (nrow, ncol) });
+        }
+        for i in 0..n {
+            if self.lu[(i, i)].abs() <= self.tol {
+                return Err(LinalgError::Singular { index: i });
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn solve(&self, b:&[f64]) -> Result<Vec<f64>, LinalgError> {
+        self.check_solvable(b.len(), 1)?;
+        check_finite(b)?;
+
+        let n = self.lu.nrow;
+        let mut x:Vec<f64> = self.perm.iter().map(|&i| b[i]).collect();
+
+        for i in 0..n {
+            let p = dot_product_simd(&self.lu.row(i)[0..i], &x[0..i]);
+            x[i] -= p;
+        }
+
+        for i in (0..n).rev() {
+            let p = dot_product_simd(&self.lu.row(i)[i+1..n], &x[i+1..n]);
+            x[i] = (x[i]-p)/self.lu[(i, i)];
+        }
+
+        let mut out = vec![0.0;n];
+        for i in 0..n {
+            out[self.col_perm[i]] = x[i];
+        }
+
+        check_finite(&out)?;
+
+        return Ok(out);
+    }
+
+    // Solves A'x = b with the same factors: U'L'(Px) = Q'b.
+    pub fn solve_transpose(&self, b:&[f64]) -> Result<Vec<f64>, LinalgError> {
+        self.check_solvable(b.len(), 1)?;
+        check_finite(b)?;
+
+        let n = self.lu.nrow;
+        let mut y:Vec<f64> = self.col_perm.iter().map(|&j| b[j]).collect();
+
+        for i in 0..n {
+            y[i] /= self.lu[(i, i)];
+            let h = y[i];
+            mul_sub_const(&self.lu.row(i)[i+1..n], &mut y[i+1..n], h, n-i-1);
+        }
+
+        for i in (0..n).rev() {
+            let h = y[i];
+            mul_sub_const(&self.lu.row(i)[0..i], &mut y[0..i], h, i);
+        }
+
+        let mut out = vec![0.0;n];
+        for i in 0..n {
+            out[self.perm[i]] = y[i];
+        }
+
+        check_finite(&out)?;
+
+        return Ok(out);
+    }
+
+    // Hager's estimate of ||A^-1||_1 with Higham's refinements: at most five pairs of solves
+    // with A and A', plus one extra solve against an alternating test vector that catches
+    // matrices where the gradient search stalls.
+    pub fn inverse_norm1_estimate(&self) -> Result<f64, LinalgError> {
+        self.check_solvable(self.lu.nrow, 1)?;
+
+        let n = self.lu.nrow;
+        let mut x = vec![1.0/(n as f64);n];
+        let mut est = 0.0;
+        let mut last_j = n;
+
+        for k in 0..5 {
+            let y = self.solve(&x)?;
+            let y_norm:f64 = y.iter().map(|v| v.abs()).sum();
+
+            if k > 0 && y_norm <= est {
+                break;
+            }
+            est = y_norm;
+
+            let xi:Vec<f64> = y.iter().map(|&v| if v >= 0.0 { 1.0 } else { -1.0 }).collect();
+            let z = self.solve_transpose(&xi)?;
+
+            let mut j = 0;
+            for i in 1..n {
+                if z[i].abs() > z[j].abs() {
+                    j = i;
+                }
+            }
+
+            if k > 0 && (z[j].abs() <= dot_product(&z, &x) || j == last_j) {
+                break;
+            }
+            last_j = j;
+
+            x = vec![0.0;n];
+            x[j] = 1.0;
+        }
+
+        let alt:Vec<f64> = (0..n).map(|i| {
+            let s = if i % 2 == 0 { 1.0 } else { -1.0 };
+            return s*(1.0+(i as f64)/((n as f64-1.0).max(1.0)));
+        }).collect();
+
+        let y = self.solve(&alt)?;
+        let alt_est = 2.0*y.iter().map(|v| v.abs()).sum::<f64>()/(3.0*n as f64);
+
+        return Ok(est.max(alt_est));
+    }
 
-    for j in 0..m {
-        let mut mmax:f64 = MIN;
-        let mut mmax_i:usize = j;
+    // Estimate of the 1-norm condition number ||A||_1 ||A^-1||_1.
+    pub fn cond1_estimate(&self) -> Result<f64, LinalgError> {
+        return Ok(self.anorm1*self.inverse_norm1_estimate()?);
+    }
+
+    // Solves AX = B for every column of B at once.
+    pub fn solve_matrix(&self, b:&Matrix) -> Result<Matrix, LinalgError> {
+        self.check_solvable(b.nrow, b.ncol)?;
+        check_finite(b.as_slice())?;
+
+        let n = self.lu.nrow;
+        let k = b.ncol;
+        let mut x = Matrix::zeros(n, k);
+
+        for i in 0..n {
+            x.row_mut(i).copy_from_slice(b.row(self.perm[i]));
+        }
 
-        for i in j..n {
-            if u[i*m+j] > mmax {
-                mmax = u[i*m+j];
-                mmax_i = i;
+        for i in 0..n {
+            for j in 0..i {
+                let h = self.lu[(i, j)];
+                if h != 0.0 {
+                    reduce_row(x.as_mut_slice(), k, j, i, h);
+                }
             }
         }
 
-        if mmax_i != j {
-            swap_rows(&mut u, m, j, mmax_i);
-            swap_rows(&mut eye, n, j, mmax_i);
-            swap_rows(&mut l, n, j, mmax_i);
+        for i in (0..n).rev() {
+            for j in i+1..n {
+                let h = self.lu[(i, j)];
+                if h != 0.0 {
+                    reduce_row(x.as_mut_slice(), k, j, i, h);
+                }
+            }
+            let d = self.lu[(i, i)];
+            for v in x.row_mut(i) {
+                *v /= d;
+            }
+        }
+
+        let mut out = Matrix::zeros(n, k);
+        for i in 0..n {
+            out.row_mut(self.col_perm[i]).copy_from_slice(x.row(i));
+        }
+
+        check_finite(out.as_slice())?;
+
+        return Ok(out);
+    }
+
+    pub fn det(&self) -> Result<f64, LinalgError> {
+        check_square(self.lu.nrow, self.lu.ncol)?;
+        let mut d = self.sign;
+        for i in 0..self.lu.nrow {
+            d *= self.lu[(i, i)];
         }
+        return Ok(d);
+    }
 
-        for i in j+1..n {
-            if u[i*m + j] != 0.0 {
-                let h = u[i*m+j]/u[j*m+j];
-                reduce_row(&mut u, m, j, i, h);
-                l[i*n+j] = h;
+    // Sign of the determinant and log|det A|, which stays finite where det() would overflow.
+    pub fn log_abs_det(&self) -> Result<(f64, f64), LinalgError> {
+        check_square(self.lu.nrow, self.lu.ncol)?;
+        let mut sign = self.sign;
+        let mut logdet = 0.0;
+        for i in 0..self.lu.nrow {
+            let x = self.lu[(i, i)];
+            if x == 0.0 {
+                return Ok((0.0, f64::NEG_INFINITY));
             }
+            sign *= x.signum();
+            logdet += x.abs().ln();
         }
+        return Ok((sign, logdet));
     }
 
-    for i in 0..n {
-        l[i*n+i] = 1.0;
+    pub fn inverse(&self) -> Result<Matrix, LinalgError> {
+        return self.solve_matrix(&Matrix::identity(self.lu.nrow));
     }
+}
 
-    return (eye, l, u);
+pub fn lu_decomposition(a:&Matrix) -> Result<(Matrix, Matrix, Matrix), LinalgError> {
+    let lu = LuFactorization::new(a)?;
+    return Ok((lu.p(), lu.l(), lu.u()));
 }
 
-pub fn run() {
-    let n = 1234;
-    let m = 569;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
 
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    
-    let mut a:Vec<f64> = vec![0.0;n*m];
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
 
-    for i in 0..n*m {
-        a[i] = normal.sample(&mut rng);
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
     }
 
-    let lu = lu_decomposition(&a, n, m);
-    
-    let eye = lu.0;
-    let l = lu.1;
-    let u = lu.2;
+    #[test]
+    fn factors_reconstruct_pa() {
+        let a = random(9, 9, 1);
+        let lu = LuFactorization::new(&a).unwrap();
+        assert!(max_abs_diff(&(lu.p()*a.clone()), &(lu.l()*lu.u())) < 1e-12);
+    }
 
-    let x = matrix_multiply_simd(&eye, &a, n, n, m);
-    let y = matrix_multiply_simd(&l, &u, n, n, m);
+    #[test]
+    fn solve_matrix_matches_column_solves() {
+        let a = random(12, 12, 2);
+        let b = random(12, 3, 3);
+        let lu = LuFactorization::new(&a).unwrap();
+        let x = lu.solve_matrix(&b).unwrap();
+        assert!(max_abs_diff(&(a.clone()*x.clone()), &b) < 1e-10);
+        for j in 0..3 {
+            let xj = lu.solve(&b.column(j)).unwrap();
+            for i in 0..12 {
+                assert!((xj[i]-x[(i, j)]).abs() < 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn det_and_inverse() {
+        let a = Matrix::new(3, 3, vec![0.0, 2.0, 1.0, 1.0, 1.0, 0.0, 3.0, 0.0, 1.0]);
+        let lu = LuFactorization::new(&a).unwrap();
+        assert!((lu.det().unwrap()-(-5.0)).abs() < 1e-12);
+        let (sign, logdet) = lu.log_abs_det().unwrap();
+        assert_eq!(sign, -1.0);
+        assert!((logdet-5.0f64.ln()).abs() < 1e-12);
+        assert!(max_abs_diff(&(a*lu.inverse().unwrap()), &Matrix::identity(3)) < 1e-12);
+    }
+
+    #[test]
+    fn singular_matrix_is_reported() {
+        let a = Matrix::new(3, 3, vec![1.0, 2.0, 3.0, 2.0, 4.0, 6.0, 1.0, 0.0, 1.0]);
+        let lu = LuFactorization::new(&a);
+        let solved = lu.and_then(|lu| lu.solve(&[1.0, 1.0, 1.0]));
+        assert!(matches!(solved, Err(LinalgError::Singular { .. })));
+    }
+
+    const MODES:[Pivoting;3] = [Pivoting::Partial, Pivoting::Rook, Pivoting::Complete];
+
+    #[test]
+    fn every_pivoting_mode_reconstructs_paq() {
+        for &(n, m) in &[(7, 7), (9, 5), (5, 9)] {
+            let a = random(n, m, 4);
+            for &pivoting in &MODES {
+                let lu = LuFactorization::with_options(&a, &LuOptions::new(pivoting, None)).unwrap();
+                let paq = lu.p()*a.clone()*lu.q();
+                assert!(max_abs_diff(&paq, &(lu.l()*lu.u())) < 1e-12, "{:?} {}x{}", pivoting, n, m);
+                assert_eq!(lu.rank(), min(n, m));
+            }
+        }
+    }
 
-    for i in 0..n*m {
-        if (x[i]-y[i]).abs()/x[i].abs() > 0.001  {
-            println!("{:?}, {:?}, {:?}", i, x[i], y[i]);
+    #[test]
+    fn rank_deficient_input_is_detected() {
+        let a = random(8, 2, 5)*random(2, 6, 6);
+        for &pivoting in &MODES {
+            let lu = LuFactorization::with_options(&a, &LuOptions::new(pivoting, None)).unwrap();
+            assert_eq!(lu.rank(), 2, "{:?}", pivoting);
+            let paq = lu.p()*a.clone()*lu.q();
+            assert!(max_abs_diff(&paq, &(lu.l()*lu.u())) < 1e-12);
         }
     }
 
+    #[test]
+    fn determinant_agrees_across_modes() {
+        let a = random(10, 10, 7);
+        let d = LuFactorization::new(&a).unwrap().det().unwrap();
+        for &pivoting in &MODES {
+            let lu = LuFactorization::with_options(&a, &LuOptions::new(pivoting, None)).unwrap();
+            assert!((lu.det().unwrap()-d).abs() <= 1e-12*d.abs().max(1.0), "{:?}", pivoting);
+        }
+    }
+
+    #[test]
+    fn solve_transpose_matches_transposed_matrix() {
+        let a = random(11, 11, 8);
+        let b = random(11, 1, 9).into_vec();
+        for &pivoting in &MODES {
+            let lu = LuFactorization::with_options(&a, &LuOptions::new(pivoting, None)).unwrap();
+            let x = lu.solve_transpose(&b).unwrap();
+            let y = LuFactorization::new(&a.transpose()).unwrap().solve(&b).unwrap();
+            for i in 0..11 {
+                assert!((x[i]-y[i]).abs() < 1e-10, "{:?}", pivoting);
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_norm_estimate_is_a_tight_lower_bound() {
+        for seed in 10..15 {
+            let a = random(25, 25, seed);
+            let lu = LuFactorization::new(&a).unwrap();
+            let exact = lu.inverse().unwrap().norm1();
+            let est = lu.inverse_norm1_estimate().unwrap();
+            assert!(est <= exact*(1.0+1e-10), "{} > {}", est, exact);
+            assert!(est >= exact/3.0, "{} << {}", est, exact);
+        }
+    }
 }
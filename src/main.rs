@@ -1,20 +1,35 @@
-#![allow(unused_attributes)]
-#![feature(portable_simd)]
-#[global_allocator]
-static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
-mod matrix_utils;
-mod orthogonal_matrices;
-mod row_echelon;
-mod reduced_row_echelon;
-mod lu_decomposition;
-mod solve_linear;
-mod linear_regression_gd;
-mod matrix_inverse;
-mod qr_decomposition;
-mod eigenvalues;
-mod sparse_matrix;
-mod svd;
+use rust_machine_learning::prelude::*;
+use rand_distr::{Distribution, Normal};
+use rand::thread_rng;
 
+// Solves a random 145x145 system and prints the rows whose residual is above 0.1%, as the
+// binary always has; the other demos live under examples/.
+#[allow(clippy::needless_range_loop)]
 fn main() {
-    solve_linear::run();
+    let n = 145;
+
+    let mut rng = thread_rng();
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
+
+    let mut a = Matrix::zeros(n, n);
+    let mut b:Vec<f64> = vec![0.0;n];
+
+    for i in 0..n {
+        for j in 0..n {
+            a[(i, j)] = normal.sample(&mut rng);
+        }
+    }
+
+    for i in 0..n {
+        b[i] = normal.sample(&mut rng);
+    }
+
+    let x = solve(&a, &b).unwrap();
+    let b1 = &a * &Matrix::column_vector(&x);
+
+    for i in 0..n {
+        if (b[i]-b1[(i, 0)]).abs()/b[i].abs() > 0.001  {
+            println!("{:?}, {:?}, {:?}", i, b[i], b1[(i, 0)]);
+        }
+    }
 }
@@ -0,0 +1,430 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::complex::Complex;
+use crate::lu_decomposition::LuFactorization;
+use crate::symmetric_eigen::symmetric_eigen;
+use crate::general_eigen::real_schur;
+
+// Pade coefficients b_0..b_m of degrees 3, 5, 7, 9 and 13 for exp, with the largest 1-norms
+// theta_m for which each keeps the backward error below eps (Higham 2005).
+const PADE3:[f64;4] = [120.0, 60.0, 12.0, 1.0];
+const PADE5:[f64;6] = [30240.0, 15120.0, 3360.0, 420.0, 30.0, 1.0];
+const PADE7:[f64;8] = [17297280.0, 8648640.0, 1995840.0, 277200.0, 25200.0, 1512.0, 56.0, 1.0];
+const PADE9:[f64;10] = [17643225600.0, 8821612800.0, 2075673600.0, 302702400.0, 30270240.0, 2162160.0, 110880.0, 3960.0, 90.0, 1.0];
+const PADE13:[f64;14] = [
+    64764752532480000.0, 32382376266240000.0, 7771770303897600.0, 1187353796428800.0,
+    129060195264000.0, 10559470521600.0, 670442572800.0, 33522128640.0, 1323241920.0,
+    40840800.0, 960960.0, 16380.0, 182.0, 1.0,
+];
+const THETA:[f64;5] = [1.495585217958292e-2, 2.53939833006323e-1, 9.504178996162932e-1, 2.097847961257068, 5.371920351148152];
+
+// Square roots are taken until ||T - I||_1 is at most LOG_THETA, where the degree LOG_DEGREE
+// Pade approximant of log(I + X) is accurate to eps; MAX_SQRTM bounds the number taken.
+const LOG_THETA:f64 = 0.25;
+const LOG_DEGREE:usize = 8;
+const MAX_SQRTM:usize = 64;
+
+// sum c_k X_k + d I for square X_k of order n.
+fn combine(n:usize, terms:&[(f64, &Matrix)], d:f64) -> Matrix {
+    let mut out = Matrix::zeros(n, n);
+    for &(c, x) in terms {
+        for (o, v) in out.as_mut_slice().iter_mut().zip(x.as_slice()) {
+            *o += c*v;
+        }
+    }
+    for i in 0..n {
+        out[(i, i)] += d;
+    }
+    return out;
+}
+
+// U (odd part) and V (even part) of the degree m Pade approximant r_m(A) = (V - U)^-1 (V + U).
+fn pade(a:&Matrix, m:usize) -> (Matrix, Matrix) {
+    let n = a.nrow;
+    let a2 = a*a;
+
+    if m == 13 {
+        let b = &PADE13;
+        let a4 = &a2*&a2;
+        let a6 = &a4*&a2;
+        let u1 = combine(n, &[(b[13], &a6), (b[11], &a4), (b[9], &a2)], 0.0);
+        let u2 = combine(n, &[(b[7], &a6), (b[5], &a4), (b[3], &a2)], b[1]);
+        let u = a*&(&(&a6*&u1)+&u2);
+        let v1 = combine(n, &[(b[12], &a6), (b[10], &a4), (b[8], &a2)], 0.0);
+        let v2 = combine(n, &[(b[6], &a6), (b[4], &a4), (b[2], &a2)], b[0]);
+        let v = &(&a6*&v1)+&v2;
+        return (u, v);
+    }
+
+    let b:&[f64] = match m {
+        3 => &PADE3,
+        5 => &PADE5,
+        7 => &PADE7,
+        _ => &PADE9,
+    };
+    let mut powers = vec![a2];
+    while powers.len() < (m-1)/2 {
+        let next = &powers[powers.len()-1]*&powers[0];
+        powers.push(next);
+    }
+    let odd:Vec<(f64, &Matrix)> = powers.iter().enumerate().map(|(k, p)| (b[2*k+3], p)).collect();
+    let even:Vec<(f64, &Matrix)> = powers.iter().enumerate().map(|(k, p)| (b[2*k+2], p)).collect();
+    let u = a*&combine(n, &odd, b[1]);
+    let v = combine(n, &even, b[0]);
+    return (u, v);
+}
+
+// Matrix exponential by scaling and squaring: the lowest Pade degree whose theta bounds ||A||_1,
+// else degree 13 on A/2^s squared s times. The rational approximant is applied through an LU
+// solve of (V - U) X = V + U.
+pub fn expm(a:&Matrix) -> Result<Matrix, LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
+
+    let n = a.nrow;
+    let anorm = a.norm1();
+
+    let mut s = 0;
+    let mut degree = 13;
+    for (k, &m) in [3, 5, 7, 9].iter().enumerate() {
+        if anorm <= THETA[k] {
+            degree = m;
+            break;
+        }
+    }
+    let scaled = if degree == 13 && anorm > THETA[4] {
+        s = (anorm/THETA[4]).log2().ceil() as i32;
+        a*0.5f64.powi(s)
+    }
+    else {
+        a.clone()
+    };
+
+    let (u, v) = pade(&scaled, degree);
+    let lu = LuFactorization::new(&combine(n, &[(1.0, &v), (-1.0, &u)], 0.0))?;
+    let mut x = lu.solve_matrix(&combine(n, &[(1.0, &v), (1.0, &u)], 0.0))?;
+
+    for _ in 0..s {
+        x = &x*&x;
+    }
+
+    check_finite(x.as_slice())?;
+    return Ok(x);
+}
+
+// V diag(f) V' for the orthonormal eigenvectors V of a symmetric matrix.
+fn symmetric_function(v:&Matrix, f:&[f64]) -> Matrix {
+    let mut vf = v.clone();
+    for i in 0..vf.nrow {
+        for (x, &d) in vf.row_mut(i).iter_mut().zip(f) {
+            *x *= d;
+        }
+    }
+    return vf.mul_tr(v);
+}
+
+// (start, size) of the 1x1 and 2x2 diagonal blocks of quasi upper triangular T.
+fn schur_blocks(t:&Matrix) -> Vec<(usize, usize)> {
+    let n = t.nrow;
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let size = if i+1 < n && t[(i+1, i)] != 0.0 { 2 } else { 1 };
+        blocks.push((i, size));
+        i += size;
+    }
+    return blocks;
+}
+
+// Principal square root of the quasi upper triangular T from a real Schur form (Higham 1987).
+// The diagonal blocks are rooted directly: a 2x2 block B with eigenvalues theta +- i mu has
+// root alpha I + (B - theta I)/(2 alpha) for alpha = Re sqrt(theta + i mu). The blocks above
+// the diagonal then follow column by column from R_ii R_ij + R_ij R_jj = T_ij - sum R_ik R_kj.
+fn quasi_triangular_sqrt(t:&Matrix) -> Result<Matrix, LinalgError> {
+    let n = t.nrow;
+    let blocks = schur_blocks(t);
+    let mut r = Matrix::zeros(n, n);
+
+    for &(i, size) in blocks.iter() {
+        if size == 1 {
+            if t[(i, i)] < 0.0 {
+                return Err(LinalgError::NegativeEigenvalue { index: i });
+            }
+            r[(i, i)] = t[(i, i)].sqrt();
+        }
+        else {
+            let (a, b, c, d) = (t[(i, i)], t[(i, i+1)], t[(i+1, i)], t[(i+1, i+1)]);
+            let theta = 0.5*(a+d);
+            let mu = (-(0.25*(a-d)*(a-d)+b*c)).max(0.0).sqrt();
+            let alpha = Complex::new(theta, mu).sqrt().re;
+            r[(i, i)] = alpha+(a-theta)/(2.0*alpha);
+            r[(i, i+1)] = b/(2.0*alpha);
+            r[(i+1, i)] = c/(2.0*alpha);
+            r[(i+1, i+1)] = alpha+(d-theta)/(2.0*alpha);
+        }
+    }
+
+    for jb in 1..blocks.len() {
+        let (j, q) = blocks[jb];
+        for ib in (0..jb).rev() {
+            let (i, p) = blocks[ib];
+
+            // Right-hand side, and the pq x pq system for X vectorized by rows.
+            let mut rhs = vec![0.0;p*q];
+            for x in 0..p {
+                for y in 0..q {
+                    let mut v = t[(i+x, j+y)];
+                    for k in i+p..j {
+                        v -= r[(i+x, k)]*r[(k, j+y)];
+                    }
+                    rhs[x*q+y] = v;
+                }
+            }
+            let mut m = Matrix::zeros(p*q, p*q);
+            for x in 0..p {
+                for y in 0..q {
+                    for k in 0..p {
+                        m[(x*q+y, k*q+y)] += r[(i+x, i+k)];
+                    }
+                    for k in 0..q {
+                        m[(x*q+y, x*q+k)] += r[(j+k, j+y)];
+                    }
+                }
+            }
+
+            let sol = LuFactorization::new(&m)?.solve(&rhs)?;
+            for x in 0..p {
+                for y in 0..q {
+                    r[(i+x, j+y)] = sol[x*q+y];
+                }
+            }
+        }
+    }
+
+    return Ok(r);
+}
+
+// Principal square root X of A, X^2 = A, with eigenvalues in the right half plane. Symmetric A
+// goes through its eigendecomposition and must be positive semidefinite, other A through the
+// real Schur form. A real negative eigenvalue gives NegativeEigenvalue, as its root is not real.
+pub fn sqrtm(a:&Matrix) -> Result<Matrix, LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
+
+    if a.is_symmetric(0.0) {
+        let (w, v) = symmetric_eigen(a)?;
+        let n = w.len();
+        let wmax = w.iter().fold(0.0, |acc:f64, x| acc.max(x.abs()));
+        let tol = (n as f64)*f64::EPSILON*wmax;
+
+        let mut f = vec![0.0;n];
+        for i in 0..n {
+            if w[i] < -tol {
+                return Err(LinalgError::NegativeEigenvalue { index: i });
+            }
+            f[i] = w[i].max(0.0).sqrt();
+        }
+        return Ok(symmetric_function(&v, &f));
+    }
+
+    let (t, z) = real_schur(a)?;
+    let r = quasi_triangular_sqrt(&t)?;
+    return Ok((&z*&r).mul_tr(&z));
+}
+
+// Nodes and weights of the m point Gauss-Legendre rule on [0, 1], by Newton's method on the
+// Legendre polynomial from the Chebyshev-like initial guesses.
+fn gauss_legendre(m:usize) -> (Vec<f64>, Vec<f64>) {
+    let mut nodes = vec![0.0;m];
+    let mut weights = vec![0.0;m];
+
+    for i in 0..m {
+        let mut x = (std::f64::consts::PI*(i as f64+0.75)/(m as f64+0.5)).cos();
+        let mut dp = 1.0;
+        for _ in 0..100 {
+            let (mut p0, mut p1) = (1.0, x);
+            for k in 2..=m {
+                let p2 = ((2*k-1) as f64*x*p1-(k-1) as f64*p0)/(k as f64);
+                p0 = p1;
+                p1 = p2;
+            }
+            dp = (m as f64)*(x*p1-p0)/(x*x-1.0);
+            let dx = p1/dp;
+            x -= dx;
+            if dx.abs() <= f64::EPSILON {
+                break;
+            }
+        }
+        nodes[i] = 0.5*(1.0-x);
+        weights[i] = 1.0/((1.0-x*x)*dp*dp);
+    }
+
+    return (nodes, weights);
+}
+
+// Principal logarithm X of A, exp(X) = A, with eigenvalue imaginary parts in (-pi, pi).
+// Symmetric A goes through its eigendecomposition. Other A uses inverse scaling and squaring on
+// the real Schur form: s square roots bring T near I, then log(T) = 2^s r(T^(1/2^s) - I) with
+// the Pade approximant r of log(I + X) in its partial fraction form sum w_j X (I + x_j X)^-1,
+// whose nodes and weights are those of Gauss-Legendre quadrature. A zero eigenvalue gives
+// Singular and a real negative one NegativeEigenvalue.
+pub fn logm(a:&Matrix) -> Result<Matrix, LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
+
+    if a.is_symmetric(0.0) {
+        let (w, v) = symmetric_eigen(a)?;
+        for (i, &x) in w.iter().enumerate() {
+            if x < 0.0 {
+                return Err(LinalgError::NegativeEigenvalue { index: i });
+            }
+            if x == 0.0 {
+                return Err(LinalgError::Singular { index: i });
+            }
+        }
+        let f:Vec<f64> = w.iter().map(|x| x.ln()).collect();
+        return Ok(symmetric_function(&v, &f));
+    }
+
+    let n = a.nrow;
+    let (mut t, z) = real_schur(a)?;
+    for &(i, size) in schur_blocks(&t).iter() {
+        if size == 1 && t[(i, i)] < 0.0 {
+            return Err(LinalgError::NegativeEigenvalue { index: i });
+        }
+        if size == 1 && t[(i, i)] == 0.0 {
+            return Err(LinalgError::Singular { index: i });
+        }
+    }
+
+    let mut s = 0;
+    let mut x = combine(n, &[(1.0, &t)], -1.0);
+    while x.norm1() > LOG_THETA {
+        if s == MAX_SQRTM {
+            return Err(LinalgError::NotConverged { iterations: s });
+        }
+        t = quasi_triangular_sqrt(&t)?;
+        x = combine(n, &[(1.0, &t)], -1.0);
+        s += 1;
+    }
+
+    let (nodes, weights) = gauss_legendre(LOG_DEGREE);
+    let mut l = Matrix::zeros(n, n);
+    for (&xj, &wj) in nodes.iter().zip(weights.iter()) {
+        let lu = LuFactorization::new(&combine(n, &[(xj, &x)], 1.0))?;
+        let y = lu.solve_matrix(&x)?;
+        l = combine(n, &[(1.0, &l), (wj, &y)], 0.0);
+    }
+    let l = &l*2.0f64.powi(s as i32);
+
+    let out = (&z*&l).mul_tr(&z);
+    check_finite(out.as_slice())?;
+    return Ok(out);
+}
+
+// A^p = V diag(w^p) V' for symmetric positive definite A and any real p. Only the lower
+// triangle of A is read; a non-positive eigenvalue gives NotPositiveDefinite at its index in
+// ascending order.
+pub fn powm(a:&Matrix, p:f64) -> Result<Matrix, LinalgError> {
+    let (w, v) = symmetric_eigen(a)?;
+    for (i, &x) in w.iter().enumerate() {
+        if x <= 0.0 {
+            return Err(LinalgError::NotPositiveDefinite { index: i });
+        }
+    }
+    let f:Vec<f64> = w.iter().map(|x| x.powf(p)).collect();
+    return Ok(symmetric_function(&v, &f));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    #[test]
+    fn expm_of_known_matrices() {
+        // exp of a rotation generator is the rotation, and exp of a nilpotent N is I + N.
+        let theta:f64 = 2.5;
+        let a = Matrix::new(2, 2, vec![0.0, -theta, theta, 0.0]);
+        let r = Matrix::new(2, 2, vec![theta.cos(), -theta.sin(), theta.sin(), theta.cos()]);
+        assert!(max_abs_diff(&expm(&a).unwrap(), &r) < 1e-14);
+
+        let n = Matrix::new(2, 2, vec![0.0, 3.0, 0.0, 0.0]);
+        assert!(max_abs_diff(&expm(&n).unwrap(), &Matrix::new(2, 2, vec![1.0, 3.0, 0.0, 1.0])) < 1e-15);
+
+        // Large norm goes through scaling and squaring.
+        let d = Matrix::from_diag(&[-20.0, 0.5, 10.0]);
+        let e = expm(&d).unwrap();
+        for (i, x) in [-20.0f64, 0.5, 10.0].iter().enumerate() {
+            assert!((e[(i, i)]-x.exp()).abs() <= 1e-13*x.exp());
+        }
+    }
+
+    #[test]
+    fn expm_and_logm_round_trip() {
+        let a = &random(6, 6, 1)*0.5;
+        let e = expm(&a).unwrap();
+        assert!(max_abs_diff(&logm(&e).unwrap(), &a) < 1e-10);
+
+        let b = &(&Matrix::identity(6)*3.0) + &random(6, 6, 2);
+        assert!(max_abs_diff(&expm(&logm(&b).unwrap()).unwrap(), &b) < 1e-10);
+
+        let c = random(5, 5, 3);
+        let s = &c.tr_mul(&c) + &Matrix::identity(5);
+        assert!(max_abs_diff(&expm(&logm(&s).unwrap()).unwrap(), &s) < 1e-10);
+    }
+
+    #[test]
+    fn sqrtm_squares_back() {
+        let b = &(&Matrix::identity(7)*3.0) + &random(7, 7, 4);
+        let x = sqrtm(&b).unwrap();
+        assert!(max_abs_diff(&(&x*&x), &b) < 1e-10);
+
+        // Complex conjugate eigenvalues: the real root of a scaled rotation.
+        let r = Matrix::new(2, 2, vec![1.0, -2.0, 2.0, 1.0]);
+        let x = sqrtm(&r).unwrap();
+        assert!(max_abs_diff(&(&x*&x), &r) < 1e-12);
+        assert!(x[(0, 0)] > 0.0);
+
+        let c = random(4, 4, 5);
+        let s = c.tr_mul(&c);
+        let x = sqrtm(&s).unwrap();
+        assert!(max_abs_diff(&(&x*&x), &s) < 1e-12);
+        assert!(max_abs_diff(&x, &x.transpose()) < 1e-14);
+    }
+
+    #[test]
+    fn powm_matches_repeated_products_and_roots() {
+        let c = random(5, 5, 6);
+        let s = &c.tr_mul(&c) + &Matrix::identity(5);
+        assert!(max_abs_diff(&powm(&s, 2.0).unwrap(), &(&s*&s)) < 1e-12);
+        assert!(max_abs_diff(&powm(&s, 0.5).unwrap(), &sqrtm(&s).unwrap()) < 1e-12);
+        let inv = powm(&s, -1.0).unwrap();
+        assert!(max_abs_diff(&(&inv*&s), &Matrix::identity(5)) < 1e-12);
+    }
+
+    #[test]
+    fn negative_and_zero_eigenvalues_are_rejected() {
+        let d = Matrix::from_diag(&[2.0, -1.0]);
+        assert!(matches!(sqrtm(&d), Err(LinalgError::NegativeEigenvalue { .. })));
+        assert!(matches!(logm(&d), Err(LinalgError::NegativeEigenvalue { .. })));
+        assert!(matches!(powm(&d, 0.5), Err(LinalgError::NotPositiveDefinite { .. })));
+
+        let t = Matrix::new(2, 2, vec![-1.0, 1.0, 0.0, 2.0]);
+        assert!(matches!(sqrtm(&t), Err(LinalgError::NegativeEigenvalue { .. })));
+        let z = Matrix::new(2, 2, vec![0.0, 1.0, 0.0, 2.0]);
+        assert!(matches!(logm(&z), Err(LinalgError::Singular { .. })));
+        assert!(matches!(expm(&Matrix::zeros(2, 3)), Err(LinalgError::NotSquare { .. })));
+    }
+}
@@ -1,62 +1,81 @@
-#![allow(dead_code)]
-use crate::solve_linear::solve;
-use rand_distr::{Distribution, Normal};
-use rand::thread_rng;
-use std::thread;
-use std::sync::Arc;
-use std::cmp::min;
-
-pub fn inverse(inp:&[f64], n:usize) -> Vec<f64>{
-    let mut sol:Vec<f64> = vec![0.0;n*n];
-    let a = Arc::new(inp.to_owned());
-
-    let mut handles = vec![];
-    let q = (n as f64/4.0).ceil() as usize;
-
-    for i in (0..n).step_by(q) {
-        let y = Arc::clone(&a);
-        let mut results = vec![];
-        
-        let handle = thread::spawn(move || {
-            for j in i..min(i+q, n) {
-                let mut b:Vec<f64> = vec![0.0;n];
-                b[j] = 1.0;
-                let x = solve(&y, &b, n);
-                results.push((x, j));
-            }
-
-            return results;
-        });
-
-        handles.push(handle);
-    }
+#![allow(dead_code, clippy::needless_return)]
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::lu_decomposition::LuFactorization;
+use crate::svd::{svd, numerical_rank, SvdMode};
+
+pub fn inverse(inp:&Matrix) -> Result<Matrix, LinalgError> {
+    check_square(inp.nrow, inp.ncol)?;
+    return LuFactorization::new(inp)?.inverse();
+}
+
+// Moore-Penrose pseudo-inverse A+ = V S+ U', m x n. Singular values at or below rcond*s_max are
+// treated as zero; rcond defaults to max(n, m)*eps.
+pub fn pinv(a:&Matrix, rcond:Option<f64>) -> Result<Matrix, LinalgError> {
+    let (n, m) = a.shape();
+    let f = svd(a, SvdMode::Thin)?;
+    let rank = numerical_rank(&f.s, n, m, rcond);
 
-    for handle in handles {
-        let res = handle.join().unwrap();
-        for out in res {
-            for j in 0..n {
-                sol[j*n+out.1] = out.0[j];
-            }
+    // V_r S_r^-1, then times U_r'.
+    let vt = f.vt.unwrap();
+    let mut vs = Matrix::zeros(m, rank);
+    for i in 0..m {
+        for j in 0..rank {
+            vs[(i, j)] = vt[(j, i)]/f.s[j];
         }
     }
-
-    return sol;
+    let ur = f.u.unwrap().view().subview(0..n, 0..rank).to_matrix();
+    return Ok(vs.mul_tr(&ur));
 }
 
-pub fn run() {
-    let n = 500;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
 
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    
-    let mut a:Vec<f64> = vec![0.0;n*n];
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
 
-    for i in 0..n*n {
-        a[i] = normal.sample(&mut rng);
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
     }
 
-    let sol = inverse(&a, n);
+    // AXA = A, XAX = X, and AX, XA symmetric.
+    fn assert_penrose(a:&Matrix, x:&Matrix) {
+        let ax = a*x;
+        let xa = x*a;
+        assert!(max_abs_diff(&(&ax * a), a) < 1e-12);
+        assert!(max_abs_diff(&(&xa * x), x) < 1e-12);
+        assert!(max_abs_diff(&ax, &ax.transpose()) < 1e-12);
+        assert!(max_abs_diff(&xa, &xa.transpose()) < 1e-12);
+    }
 
-    println!("{:?}", a);
-    println!("{:?}", sol);
-}
\ No newline at end of file
+    #[test]
+    fn pinv_satisfies_penrose_conditions() {
+        for &(n, m) in &[(7, 4), (4, 7), (5, 5)] {
+            let a = random(n, m, (n+m) as u64);
+            let x = pinv(&a, None).unwrap();
+            assert_eq!(x.shape(), (m, n));
+            assert_penrose(&a, &x);
+        }
+
+        // Rank one: the pseudo-inverse of uv' is vu'/(|u|^2 |v|^2).
+        let u = random(5, 1, 1);
+        let v = random(3, 1, 2);
+        let a = u.mul_tr(&v);
+        let x = pinv(&a, None).unwrap();
+        assert_penrose(&a, &x);
+        let scale = u.norm().powi(2)*v.norm().powi(2);
+        assert!(max_abs_diff(&x, &(&v.mul_tr(&u)*(1.0/scale))) < 1e-12);
+    }
+
+    #[test]
+    fn pinv_of_invertible_matrix_is_inverse() {
+        let a = random(6, 6, 3);
+        assert!(max_abs_diff(&pinv(&a, None).unwrap(), &inverse(&a).unwrap()) < 1e-10);
+        assert_eq!(pinv(&Matrix::zeros(0, 3), None).unwrap().shape(), (3, 0));
+    }
+}
@@ -1,10 +1,12 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::needless_return)]
 use std::simd::prelude::*;
-use rand_distr::{Distribution, Normal};
-use rand::thread_rng;
-use std::time::SystemTime;
 use std::cmp::min;
+use crate::linalg_error::LinalgError;
+use crate::gemm::*;
+use crate::dense_matrix::Matrix;
+use crate::matrix_view::{MatrixView, MatrixViewMut};
 
+#[allow(clippy::manual_memcpy)]
 pub fn copy(a:&[f64], b:&mut [f64], n:usize) {
     const LANES:usize = 64;
 
@@ -38,7 +40,7 @@ pub fn dot_product_simd(inp1:&[f64], inp2:&[f64]) -> f64 {
 
     for i in (0..n).step_by(LANES) {
         if i+LANES > n {
-            sum += dot_product(&inp1[i..n].to_vec(), &inp2[i..n].to_vec());
+            sum += dot_product(&inp1[i..n], &inp2[i..n]);
             break;
         }
         else {
@@ -52,7 +54,57 @@ pub fn dot_product_simd(inp1:&[f64], inp2:&[f64]) -> f64 {
     return sum;
 }
 
-pub fn matrix_multiply(inp1:&[f64], inp2:&[f64], n:usize, m:usize, p:usize) -> Vec<f64> {
+// Error-free transformations: a+b = s+e and a*b = p+e exactly.
+pub fn two_sum(a:f64, b:f64) -> (f64, f64) {
+    let s = a+b;
+    let z = s-a;
+    let e = (a-(s-z))+(b-z);
+    return (s, e);
+}
+
+pub fn two_prod(a:f64, b:f64) -> (f64, f64) {
+    let p = a*b;
+    let e = a.mul_add(b, -p);
+    return (p, e);
+}
+
+// Neumaier-compensated sum of products; the products themselves are rounded.
+pub fn dot_product_compensated(inp1:&[f64], inp2:&[f64]) -> f64 {
+    let mut sum = 0.0;
+    let mut c = 0.0;
+    for i in 0..inp1.len() {
+        let (s, e) = two_sum(sum, inp1[i]*inp2[i]);
+        sum = s;
+        c += e;
+    }
+    return sum+c;
+}
+
+// Dot2 of Ogita, Rump and Oishi: as accurate as a dot product in twice the working precision.
+pub fn dot_product_extended(inp1:&[f64], inp2:&[f64]) -> f64 {
+    let mut sum = 0.0;
+    let mut c = 0.0;
+    for i in 0..inp1.len() {
+        let (p, pe) = two_prod(inp1[i], inp2[i]);
+        let (s, se) = two_sum(sum, p);
+        sum = s;
+        c += pe+se;
+    }
+    return sum+c;
+}
+
+fn check_multiply(inp1:&[f64], inp2:&[f64], n:usize, m:usize, p:usize) -> Result<(), LinalgError> {
+    if inp1.len() != n*m {
+        return Err(LinalgError::DimensionMismatch { expected: (n, m), found: (inp1.len()/m.max(1), m) });
+    }
+    if inp2.len() != m*p {
+        return Err(LinalgError::DimensionMismatch { expected: (m, p), found: (inp2.len()/p.max(1), p) });
+    }
+    return Ok(());
+}
+
+pub fn matrix_multiply(inp1:&[f64], inp2:&[f64], n:usize, m:usize, p:usize) -> Result<Vec<f64>, LinalgError> {
+    check_multiply(inp1, inp2, n, m, p)?;
     let mut out:Vec<f64> = vec![0.0;n*p];
     for i in 0..n {
         for k in 0..m {
@@ -62,10 +114,17 @@ pub fn matrix_multiply(inp1:&[f64], inp2:&[f64], n:usize, m:usize, p:usize) -> V
         }
     }
 
-    return out;
+    return Ok(out);
 }
 
-pub fn matrix_multiply_simd(inp1:&[f64], inp2:&[f64], n:usize, m:usize, p:usize) -> Vec<f64> {
+#[allow(clippy::explicit_counter_loop, clippy::needless_range_loop)]
+pub fn matrix_multiply_simd(inp1:&[f64], inp2:&[f64], n:usize, m:usize, p:usize) -> Result<Vec<f64>, LinalgError> {
+    check_multiply(inp1, inp2, n, m, p)?;
+
+    if n*m*p >= GEMM_MIN_FLOPS {
+        return gemm(inp1, inp2, n, m, p, &GemmConfig::default());
+    }
+
     const LANES:usize = 64;
     let mut out:Vec<f64> = vec![0.0;n*p];
 
@@ -90,96 +149,106 @@ pub fn matrix_multiply_simd(inp1:&[f64], inp2:&[f64], n:usize, m:usize, p:usize)
             }
         }
     }
-    return out;
+    return Ok(out);
 }
 
-pub fn matrix_multiply_simd_on_slices(inp1:&[f64], inp2:&[f64], _n1:usize, m1:usize, _n2:usize, m2:usize, x11:usize, x12:usize, y11:usize, y12:usize, x21:usize, x22:usize, y21:usize, y22:usize) -> Vec<f64> {
-    const LANES:usize = 64;
+pub fn matrix_multiply_view(a:MatrixView, b:MatrixView) -> Result<Matrix, LinalgError> {
+    if a.ncol != b.nrow {
+        return Err(LinalgError::DimensionMismatch { expected: (a.ncol, b.ncol), found: (b.nrow, b.ncol) });
+    }
 
-    let q1 = x12-x11+1;
-    let r1 = y12-y11+1;
-
-    let q2 = x22-x21+1;
-    let r2 = y22-y21+1;
-
-    let mut out:Vec<f64> = vec![0.0;q1*r2];
-
-    if r1 == q2 {
-        for i in x11..x12+1 {
-            let mut l = x21;
-            for k in y11..y12+1 {
-                let a:Simd<f64, LANES> = Simd::splat(inp1[i*m1+k]);
-                for j in (y21..y22+1).step_by(LANES) {
-                    if j+LANES > y22+1 {
-                        let mut r:usize = (i-x11)*r2+(j-y21);
-                        for h in l*m2+j..l*m2+y22+1 {
-                            out[r] += inp1[i*m1+k]*inp2[h];
-                            r += 1;
-                        }
-                    }
-                    else {
-                        let x:Simd<f64, LANES> = Simd::from_slice(&inp2[l*m2+j..l*m2+j+LANES]);
-                        let z:Simd<f64, LANES> = a*x;
-                        let mut c:Simd<f64, LANES> = Simd::from_slice(&out[(i-x11)*r2+(j-y21)..(i-x11)*r2+(j-y21)+LANES]);
-                        c += z;
-                        Simd::copy_to_slice(c, &mut out[(i-x11)*r2+(j-y21)..(i-x11)*r2+(j-y21)+LANES]);
-                    }
+    let (a_data, a_rs, a_cs) = a.as_strided_slice();
+    let (b_data, b_rs, b_cs) = b.as_strided_slice();
+    let out = gemm_strided(a_data, a_rs, a_cs, b_data, b_rs, b_cs, a.nrow, a.ncol, b.ncol, &GemmConfig::default());
+
+    return Ok(Matrix::new(a.nrow, b.ncol, out));
+}
+
+pub fn vector_matrix_multiply(u:&[f64], a:MatrixView) -> Result<Vec<f64>, LinalgError> {
+    if u.len() != a.nrow {
+        return Err(LinalgError::DimensionMismatch { expected: (a.nrow, 1), found: (u.len(), 1) });
+    }
+
+    let mut out:Vec<f64> = vec![0.0;a.ncol];
+
+    for i in 0..a.nrow {
+        match a.row_slice(i) {
+            Some(row) => mul_sub_const(row, &mut out, -u[i], a.ncol),
+            None => {
+                for j in 0..a.ncol {
+                    out[j] += u[i]*a[(i, j)];
                 }
-                l += 1;
             }
         }
     }
 
-    return out;
+    return Ok(out);
 }
 
-pub fn matrix_multiply_simd_on_slices_left_vector(inp1:&[f64], inp2:&[f64], m:usize, y11:usize, y12:usize, x21:usize, x22:usize, y21:usize, y22:usize) -> Vec<f64> {
-    const LANES:usize = 64;
+pub fn matrix_vector_multiply(a:MatrixView, u:&[f64]) -> Result<Vec<f64>, LinalgError> {
+    if u.len() != a.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (a.ncol, 1), found: (u.len(), 1) });
+    }
 
-    let r1 = y12-y11+1;
+    let mut out:Vec<f64> = vec![0.0;a.nrow];
 
-    let q2 = x22-x21+1;
-    let r2 = y22-y21+1;
+    for i in 0..a.nrow {
+        out[i] = match a.row_slice(i) {
+            Some(row) => dot_product_simd(row, u),
+            None => {
+                let mut s = 0.0;
+                for j in 0..a.ncol {
+                    s += a[(i, j)]*u[j];
+                }
+                s
+            }
+        };
+    }
 
-    let mut out:Vec<f64> = vec![0.0;r2];
+    return Ok(out);
+}
 
-    if r1 == q2 {
-        let mut l = x21;
-        for k in y11..y12+1 {
-            let a:Simd<f64, LANES> = Simd::splat(inp1[k]);
-            for j in (y21..y22+1).step_by(LANES) {
-                if j+LANES > y22+1 {
-                    let mut r:usize = j-y21;
-                    for h in l*m+j..l*m+y22+1 {
-                        out[r] += inp1[k]*inp2[h];
-                        r += 1;
-                    }
-                }
-                else {
-                    let x:Simd<f64, LANES> = Simd::from_slice(&inp2[l*m+j..l*m+j+LANES]);
-                    let z:Simd<f64, LANES> = a*x;
-                    let mut c:Simd<f64, LANES> = Simd::from_slice(&out[j-y21..j-y21+LANES]);
-                    c += z;
-                    Simd::copy_to_slice(c, &mut out[j-y21..j-y21+LANES]);
+// a -= b
+pub fn matrix_sub_assign(mut a:MatrixViewMut, b:MatrixView) {
+    assert!(a.shape() == b.shape(), "Cannot subtract {}x{} from {}x{}", b.nrow, b.ncol, a.nrow, a.ncol);
+    let m = a.ncol;
+
+    for i in 0..a.nrow {
+        match (a.row_slice_mut(i), b.row_slice(i)) {
+            (Some(row), Some(brow)) => mul_sub_const(brow, row, 1.0, m),
+            _ => {
+                for j in 0..m {
+                    a[(i, j)] -= b[(i, j)];
                 }
             }
-            l += 1;
         }
     }
-
-    return out;
 }
 
-pub fn matrix_multiply_simd_on_slices_right_vector(inp1:&[f64], inp2:&[f64], m:usize, x11:usize, x12:usize, y11:usize, y12:usize) -> Vec<f64> {
-    let mut out = vec![0.0;x12-x11+1];
+// a += alpha * x * y'
+pub fn rank_one_update(mut a:MatrixViewMut, alpha:f64, x:&[f64], y:&[f64]) -> Result<(), LinalgError> {
+    if x.len() != a.nrow || y.len() != a.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (a.nrow, a.ncol), found: (x.len(), y.len()) });
+    }
+
+    let m = a.ncol;
 
-    for i in x11..x12+1 {
-        out[i-x11] = dot_product_simd(&inp1[i*m+y11..i*m+y12+1], &inp2);
+    for i in 0..a.nrow {
+        let h = -alpha*x[i];
+        match a.row_slice_mut(i) {
+            Some(row) => mul_sub_const(y, row, h, m),
+            None => {
+                for j in 0..m {
+                    a[(i, j)] -= y[j]*h;
+                }
+            }
+        }
     }
 
-    return out;
+    return Ok(());
 }
 
+#[allow(clippy::needless_range_loop)]
 pub fn norm(a:&[f64], n:usize) -> f64{
     const LANES:usize = 64;
     let mut s = 0.0;
@@ -253,6 +322,7 @@ pub fn hypot(x:f64, y:f64) -> f64 {
     return s.abs()*u;
 }
 
+#[allow(clippy::explicit_counter_loop)]
 pub fn sub_mat(a:&[f64], _n:usize, m:usize, r_start:usize, r_end:usize, c_start:usize, c_end:usize) -> Vec<f64> {
     let mut b:Vec<f64> = vec![0.0;(r_end-r_start+1)*(c_end-c_start+1)];
     let u = c_end-c_start+1;
@@ -266,6 +336,7 @@ pub fn sub_mat(a:&[f64], _n:usize, m:usize, r_start:usize, r_end:usize, c_start:
     return b;
 }
 
+#[allow(clippy::explicit_counter_loop, clippy::too_many_arguments)]
 pub fn copy_sub_mat(a:&mut [f64], b:&[f64], _n:usize, m:usize, r_start:usize, r_end:usize, c_start:usize, c_end:usize) {
     let u = c_end-c_start+1;
     let mut k = 0;
@@ -348,37 +419,3 @@ pub fn transpose(a:&[f64], n:usize, m:usize) -> Vec<f64> {
 
     return b;
 }
-
-pub fn run() {
-    // Matrix multiplication
-    let n:usize = 101;
-    let m:usize = 511;
-    let p:usize = 397;
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    let mut inp1:Vec<f64> = vec![0.0;n*m];
-    let mut inp2:Vec<f64> = vec![0.0;m*p];
-
-    for i in 0..n*m {
-        inp1[i] = normal.sample(&mut rng);
-    }
-
-    for i in 0..m*p {
-        inp2[i] = normal.sample(&mut rng);
-    }
-
-    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
-    let prod1 = matrix_multiply_simd(&inp1, &inp2, n, m, p);
-    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
-
-    println!("{:?}", end_time-start_time);
-
-    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
-    let prod2 = matrix_multiply(&inp1, &inp2, n, m, p);
-    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_micros();
-
-    println!("{:?}", end_time-start_time);
-
-    assert!(prod1 == prod2, "Matrix multiplications results are different");
-
-}
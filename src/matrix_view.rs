@@ -0,0 +1,356 @@
+#![allow(dead_code, clippy::needless_return)]
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut, Range};
+use crate::dense_matrix::Matrix;
+
+// Borrowed, strided window into row-major storage. Element (i, j) lives at
+// ptr + i*row_stride + j*col_stride, so subviews and transposes never copy.
+#[derive(Clone, Copy)]
+pub struct MatrixView<'a> {
+    ptr: *const f64,
+    pub nrow: usize,
+    pub ncol: usize,
+    row_stride: usize,
+    col_stride: usize,
+    marker: PhantomData<&'a f64>,
+}
+
+pub struct MatrixViewMut<'a> {
+    ptr: *mut f64,
+    pub nrow: usize,
+    pub ncol: usize,
+    row_stride: usize,
+    col_stride: usize,
+    marker: PhantomData<&'a mut f64>,
+}
+
+unsafe impl Send for MatrixView<'_> {}
+unsafe impl Sync for MatrixView<'_> {}
+unsafe impl Send for MatrixViewMut<'_> {}
+unsafe impl Sync for MatrixViewMut<'_> {}
+
+fn check_strides(len:usize, offset:usize, nrow:usize, ncol:usize, row_stride:usize, col_stride:usize) {
+    if nrow > 0 && ncol > 0 {
+        let last = offset + (nrow-1)*row_stride + (ncol-1)*col_stride;
+        assert!(last < len, "View of {}x{} with strides ({}, {}) at offset {} exceeds buffer of length {}", nrow, ncol, row_stride, col_stride, offset, len);
+    }
+}
+
+// A mutable view must reach each of its elements at a distinct offset. A dimension with more
+// than one element needs a nonzero stride, and with both longer than one, the span of the
+// smaller stride must end before one step of the larger, so the offsets i*row_stride +
+// j*col_stride read as digits in mixed radix. Views built by subview and transpose always pass.
+fn check_no_alias(nrow:usize, ncol:usize, row_stride:usize, col_stride:usize) {
+    if nrow == 0 || ncol == 0 {
+        return;
+    }
+    let rows_ok = nrow <= 1 || row_stride > 0;
+    let cols_ok = ncol <= 1 || col_stride > 0;
+    let disjoint = nrow <= 1 || ncol <= 1 || if col_stride <= row_stride {
+        (ncol-1)*col_stride < row_stride
+    }
+    else {
+        (nrow-1)*row_stride < col_stride
+    };
+    assert!(rows_ok && cols_ok && disjoint, "Mutable view of {}x{} with strides ({}, {}) aliases elements", nrow, ncol, row_stride, col_stride);
+}
+
+fn check_range(rows:&Range<usize>, cols:&Range<usize>, nrow:usize, ncol:usize) {
+    assert!(rows.start <= rows.end && rows.end <= nrow, "Row range {:?} out of bounds for {} rows", rows, nrow);
+    assert!(cols.start <= cols.end && cols.end <= ncol, "Column range {:?} out of bounds for {} columns", cols, ncol);
+}
+
+impl<'a> MatrixView<'a> {
+    pub fn new(data:&'a [f64], nrow:usize, ncol:usize) -> Self {
+        return MatrixView::from_parts(data, 0, nrow, ncol, ncol, 1);
+    }
+
+    pub fn from_parts(data:&'a [f64], offset:usize, nrow:usize, ncol:usize, row_stride:usize, col_stride:usize) -> Self {
+        check_strides(data.len(), offset, nrow, ncol, row_stride, col_stride);
+
+        Self {
+            ptr: data.as_ptr().wrapping_add(offset),
+            nrow,
+            ncol,
+            row_stride,
+            col_stride,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> MatrixView<'a> {
+    pub fn shape(&self) -> (usize, usize) {
+        return (self.nrow, self.ncol);
+    }
+
+    pub fn strides(&self) -> (usize, usize) {
+        return (self.row_stride, self.col_stride);
+    }
+
+    pub fn get(&self, i:usize, j:usize) -> f64 {
+        return self[(i, j)];
+    }
+
+    pub fn subview(&self, rows:Range<usize>, cols:Range<usize>) -> MatrixView<'a> {
+        check_range(&rows, &cols, self.nrow, self.ncol);
+
+        MatrixView {
+            ptr: self.ptr.wrapping_add(rows.start*self.row_stride + cols.start*self.col_stride),
+            nrow: rows.end-rows.start,
+            ncol: cols.end-cols.start,
+            row_stride: self.row_stride,
+            col_stride: self.col_stride,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn t(&self) -> MatrixView<'a> {
+        MatrixView {
+            ptr: self.ptr,
+            nrow: self.ncol,
+            ncol: self.nrow,
+            row_stride: self.col_stride,
+            col_stride: self.row_stride,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn row(&self, i:usize) -> MatrixView<'a> {
+        return self.subview(i..i+1, 0..self.ncol);
+    }
+
+    pub fn column(&self, j:usize) -> MatrixView<'a> {
+        return self.subview(0..self.nrow, j..j+1);
+    }
+
+    // Contiguous slice for row i, available when the columns are unit-stride.
+    pub fn row_slice(&self, i:usize) -> Option<&'a [f64]> {
+        assert!(i < self.nrow, "Row {} out of bounds for {} rows", i, self.nrow);
+        if self.col_stride != 1 && self.ncol > 1 {
+            return None;
+        }
+        return Some(unsafe { std::slice::from_raw_parts(self.ptr.wrapping_add(i*self.row_stride), self.ncol) });
+    }
+
+    pub fn to_vec(&self) -> Vec<f64> {
+        let mut out = vec![0.0;self.nrow*self.ncol];
+        for i in 0..self.nrow {
+            for j in 0..self.ncol {
+                out[i*self.ncol+j] = self[(i, j)];
+            }
+        }
+        return out;
+    }
+
+    pub fn to_matrix(&self) -> Matrix {
+        return Matrix::new(self.nrow, self.ncol, self.to_vec());
+    }
+
+    // Smallest slice covering every element of the view, with the strides to walk it.
+    pub(crate) fn as_strided_slice(&self) -> (&'a [f64], usize, usize) {
+        if self.nrow == 0 || self.ncol == 0 {
+            return (&[], self.row_stride, self.col_stride);
+        }
+        let len = (self.nrow-1)*self.row_stride + (self.ncol-1)*self.col_stride + 1;
+        return (unsafe { std::slice::from_raw_parts(self.ptr, len) }, self.row_stride, self.col_stride);
+    }
+}
+
+impl Index<(usize, usize)> for MatrixView<'_> {
+    type Output = f64;
+
+    fn index(&self, (i, j):(usize, usize)) -> &f64 {
+        assert!(i < self.nrow && j < self.ncol, "Index ({}, {}) out of bounds for {}x{} view", i, j, self.nrow, self.ncol);
+        return unsafe { &*self.ptr.wrapping_add(i*self.row_stride + j*self.col_stride) };
+    }
+}
+
+impl<'a> MatrixViewMut<'a> {
+    pub fn new(data:&'a mut [f64], nrow:usize, ncol:usize) -> Self {
+        return MatrixViewMut::from_parts(data, 0, nrow, ncol, ncol, 1);
+    }
+
+    pub fn from_parts(data:&'a mut [f64], offset:usize, nrow:usize, ncol:usize, row_stride:usize, col_stride:usize) -> Self {
+        check_strides(data.len(), offset, nrow, ncol, row_stride, col_stride);
+        check_no_alias(nrow, ncol, row_stride, col_stride);
+
+        Self {
+            ptr: data.as_mut_ptr().wrapping_add(offset),
+            nrow,
+            ncol,
+            row_stride,
+            col_stride,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> MatrixViewMut<'a> {
+    pub fn shape(&self) -> (usize, usize) {
+        return (self.nrow, self.ncol);
+    }
+
+    pub fn strides(&self) -> (usize, usize) {
+        return (self.row_stride, self.col_stride);
+    }
+
+    pub fn rb(&self) -> MatrixView<'_> {
+        MatrixView {
+            ptr: self.ptr,
+            nrow: self.nrow,
+            ncol: self.ncol,
+            row_stride: self.row_stride,
+            col_stride: self.col_stride,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn rb_mut(&mut self) -> MatrixViewMut<'_> {
+        MatrixViewMut {
+            ptr: self.ptr,
+            nrow: self.nrow,
+            ncol: self.ncol,
+            row_stride: self.row_stride,
+            col_stride: self.col_stride,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn subview_mut(self, rows:Range<usize>, cols:Range<usize>) -> MatrixViewMut<'a> {
+        check_range(&rows, &cols, self.nrow, self.ncol);
+
+        MatrixViewMut {
+            ptr: self.ptr.wrapping_add(rows.start*self.row_stride + cols.start*self.col_stride),
+            nrow: rows.end-rows.start,
+            ncol: cols.end-cols.start,
+            row_stride: self.row_stride,
+            col_stride: self.col_stride,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn t_mut(self) -> MatrixViewMut<'a> {
+        MatrixViewMut {
+            ptr: self.ptr,
+            nrow: self.ncol,
+            ncol: self.nrow,
+            row_stride: self.col_stride,
+            col_stride: self.row_stride,
+            marker: PhantomData,
+        }
+    }
+
+    // Splits into rows [0, i) and [i, nrow). The halves never overlap, so both can be written.
+    pub fn split_at_row(self, i:usize) -> (MatrixViewMut<'a>, MatrixViewMut<'a>) {
+        assert!(i <= self.nrow, "Split row {} out of bounds for {} rows", i, self.nrow);
+        let (nrow, ncol) = self.shape();
+        let other = MatrixViewMut {
+            ptr: self.ptr,
+            nrow,
+            ncol,
+            row_stride: self.row_stride,
+            col_stride: self.col_stride,
+            marker: PhantomData,
+        };
+        return (self.subview_mut(0..i, 0..ncol), other.subview_mut(i..nrow, 0..ncol));
+    }
+
+    // Splits into columns [0, j) and [j, ncol).
+    pub fn split_at_col(self, j:usize) -> (MatrixViewMut<'a>, MatrixViewMut<'a>) {
+        let (top, bottom) = self.t_mut().split_at_row(j);
+        return (top.t_mut(), bottom.t_mut());
+    }
+
+    pub fn row_slice_mut(&mut self, i:usize) -> Option<&mut [f64]> {
+        assert!(i < self.nrow, "Row {} out of bounds for {} rows", i, self.nrow);
+        if self.col_stride != 1 && self.ncol > 1 {
+            return None;
+        }
+        return Some(unsafe { std::slice::from_raw_parts_mut(self.ptr.wrapping_add(i*self.row_stride), self.ncol) });
+    }
+
+    pub fn copy_from(&mut self, src:MatrixView) {
+        assert!(self.shape() == src.shape(), "Cannot copy {}x{} view into {}x{} view", src.nrow, src.ncol, self.nrow, self.ncol);
+        for i in 0..self.nrow {
+            for j in 0..self.ncol {
+                self[(i, j)] = src[(i, j)];
+            }
+        }
+    }
+
+    pub fn fill(&mut self, x:f64) {
+        for i in 0..self.nrow {
+            for j in 0..self.ncol {
+                self[(i, j)] = x;
+            }
+        }
+    }
+}
+
+impl Index<(usize, usize)> for MatrixViewMut<'_> {
+    type Output = f64;
+
+    fn index(&self, (i, j):(usize, usize)) -> &f64 {
+        assert!(i < self.nrow && j < self.ncol, "Index ({}, {}) out of bounds for {}x{} view", i, j, self.nrow, self.ncol);
+        return unsafe { &*self.ptr.wrapping_add(i*self.row_stride + j*self.col_stride) };
+    }
+}
+
+impl IndexMut<(usize, usize)> for MatrixViewMut<'_> {
+    fn index_mut(&mut self, (i, j):(usize, usize)) -> &mut f64 {
+        assert!(i < self.nrow && j < self.ncol, "Index ({}, {}) out of bounds for {}x{} view", i, j, self.nrow, self.ncol);
+        return unsafe { &mut *self.ptr.wrapping_add(i*self.row_stride + j*self.col_stride) };
+    }
+}
+
+impl Matrix {
+    pub fn view(&self) -> MatrixView<'_> {
+        return MatrixView::new(self.as_slice(), self.nrow, self.ncol);
+    }
+
+    pub fn view_mut(&mut self) -> MatrixViewMut<'_> {
+        let (nrow, ncol) = self.shape();
+        return MatrixViewMut::new(self.as_mut_slice(), nrow, ncol);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "aliases elements")]
+    fn mutable_view_rejects_zero_col_stride() {
+        let mut d = vec![0.0;6];
+        MatrixViewMut::from_parts(&mut d, 0, 3, 2, 1, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "aliases elements")]
+    fn mutable_view_rejects_zero_row_stride() {
+        let mut d = vec![0.0;6];
+        MatrixViewMut::from_parts(&mut d, 0, 2, 1, 0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "aliases elements")]
+    fn mutable_view_rejects_overlapping_rows() {
+        let mut d = vec![0.0;6];
+        MatrixViewMut::from_parts(&mut d, 0, 2, 3, 2, 1);
+    }
+
+    #[test]
+    fn mutable_view_accepts_disjoint_strides() {
+        let mut d = vec![0.0;12];
+        let v = MatrixViewMut::from_parts(&mut d, 0, 3, 4, 4, 1).t_mut();
+        let (mut l, mut r) = v.split_at_col(1);
+        l.fill(1.0);
+        r.fill(2.0);
+        assert_eq!(l.rb().get(0, 0), 1.0);
+        let mut e = vec![0.0;4];
+        MatrixViewMut::from_parts(&mut e, 0, 1, 4, 0, 1);
+        MatrixViewMut::from_parts(&mut e, 0, 2, 2, 1, 2);
+        MatrixViewMut::from_parts(&mut e, 0, 3, 0, 0, 1);
+    }
+}
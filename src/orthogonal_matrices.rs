@@ -1,124 +1,411 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::needless_return)]
 use std::cmp::min;
 use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::matrix_view::{MatrixView, MatrixViewMut};
+
+// Unit vector u such that (I - 2uu')x is a multiple of e1, or None when x is zero.
+#[allow(clippy::needless_range_loop)]
+pub fn householder_vector(x:&[f64]) -> Option<Vec<f64>> {
+    let n1 = x.len();
+    let mut nm = 0.0;
+    let mut u = x.to_vec();
+
+    for i1 in 0..n1 {
+        nm += x[i1]*x[i1];
+    }
 
-pub fn householder_reflection_left_multiply(a:&[f64], n:usize, m:usize) -> (Vec<f64>, Vec<f64>) {
-    let mut q_lt = identity(n);
-    let mut r = a.to_vec();
-    let w = min(n, m);
+    // x[0] and the norm are added with the same sign, so u[0] never cancels and u stays
+    // accurate when x is already close to a multiple of e1.
+    u[0] += sgn(x[0])*my_sqrt(nm);
+    let z = my_sqrt(2.0*(nm+x[0].abs()*my_sqrt(nm)));
+
+    if z > 0.0 {
+        for i1 in 0..n1 {
+            u[i1] /= z;
+        }
+        return Some(u);
+    }
+
+    return None;
+}
 
-    for i in 0..w {
-        let n1 = n-i;
+// a = (I - 2uu')a
+pub fn householder_left_multiply(a:MatrixViewMut, u:&[f64]) {
+    let r1 = vector_matrix_multiply(u, a.rb()).unwrap();
+    rank_one_update(a, -2.0, u, &r1).unwrap();
+}
+
+// a = a(I - 2uu')
+pub fn householder_right_multiply(a:MatrixViewMut, u:&[f64]) {
+    let r1 = matrix_vector_multiply(a.rb(), u).unwrap();
+    rank_one_update(a, -2.0, &r1, u).unwrap();
+}
+
+// Overwrites x with beta and the tail of v, where H = I - tau vv' with v[0] = 1 maps x to beta*e1.
+// Returns tau, which is zero when x is already a multiple of e1.
+#[allow(clippy::needless_range_loop)]
+pub fn householder_reflector(x:&mut [f64]) -> f64 {
+    let n1 = x.len();
+    if n1 == 0 {
+        return 0.0;
+    }
+
+    let tail = norm(&x[1..n1], n1-1);
+    if tail == 0.0 {
+        return 0.0;
+    }
 
-        let mut nm = 0.0;
-        let mut u = vec![0.0;n1];
-        let r1;
-        let q1;
+    let alpha = x[0];
+    let beta = -sgn(alpha)*alpha.hypot(tail);
+    let tau = (beta-alpha)/beta;
+    let h = 1.0/(alpha-beta);
 
-        for i1 in i..n {
-            nm += r[i1*m+i]*r[i1*m+i];
-            u[i1-i] = r[i1*m+i];
+    for i1 in 1..n1 {
+        x[i1] *= h;
+    }
+    x[0] = beta;
+
+    return tau;
+}
+
+// a = (I - tau vv')a
+pub fn apply_reflector_left(a:MatrixViewMut, v:&[f64], tau:f64) {
+    if tau == 0.0 {
+        return;
+    }
+    let w = vector_matrix_multiply(v, a.rb()).unwrap();
+    rank_one_update(a, -tau, v, &w).unwrap();
+}
+
+// a = a(I - tau vv')
+pub fn apply_reflector_right(a:MatrixViewMut, v:&[f64], tau:f64) {
+    if tau == 0.0 {
+        return;
+    }
+    let w = matrix_vector_multiply(a.rb(), v).unwrap();
+    rank_one_update(a, -tau, &w, v).unwrap();
+}
+
+// Panels of this many reflectors are accumulated into compact WY form and applied with GEMM.
+pub const QR_BLOCK:usize = 32;
+
+// Explicit unit lower trapezoidal V for the reflectors stored below the diagonal of the k
+// leading columns of a.
+pub fn reflector_panel(a:MatrixView, k:usize) -> Matrix {
+    let n = a.nrow;
+    let mut v = Matrix::zeros(n, k);
+    for j in 0..min(k, n) {
+        v[(j, j)] = 1.0;
+        for i in j+1..n {
+            v[(i, j)] = a[(i, j)];
         }
+    }
+    return v;
+}
 
-        u[0] -= sgn(r[i*m+i])*my_sqrt(nm);
-        let z = my_sqrt(nm-r[i*m+i]*r[i*m+i]+u[0]*u[0]);
+// Upper triangular T with H_0 H_1 ... H_{k-1} = I - VTV'.
+pub fn block_reflector_t(v:MatrixView, tau:&[f64]) -> Matrix {
+    let k = tau.len();
+    let mut t = Matrix::zeros(k, k);
 
-        if z > 0.0 {
-            for i1 in 0..n1 {
-                u[i1] = u[i1]/z;
-            }
+    for j in 0..k {
+        t[(j, j)] = tau[j];
 
-            r1 = matrix_multiply_simd_on_slices_left_vector(&u, &r, m,  0, n1-1, i, n-1, i, m-1);
+        if j > 0 && tau[j] != 0.0 {
+            let vj = v.column(j).to_vec();
+            let w = vector_matrix_multiply(&vj, v.subview(0..v.nrow, 0..j)).unwrap();
 
-            for i1 in i..n {
-                mul_sub_const(&r1, &mut r[i1*m+i..(i1+1)*m], 2.0*u[i1-i], m-i);
+            for r in 0..j {
+                let mut s = 0.0;
+                for c in r..j {
+                    s += t[(r, c)]*w[c];
+                }
+                t[(r, j)] = -tau[j]*s;
             }
+        }
+    }
+
+    return t;
+}
+
+// c = (I - VTV')c, or (I - VTV')'c when transpose is set.
+pub fn apply_block_reflector_left(c:MatrixViewMut, v:MatrixView, t:&Matrix, transpose:bool) {
+    let w = matrix_multiply_view(v.t(), c.rb()).unwrap();
+    let t = if transpose { t.view().t() } else { t.view() };
+    let tw = matrix_multiply_view(t, w.view()).unwrap();
+    let update = matrix_multiply_view(v, tw.view()).unwrap();
+    matrix_sub_assign(c, update.view());
+}
+
+// c = c(I - VTV'), or c(I - VTV')' when transpose is set.
+pub fn apply_block_reflector_right(c:MatrixViewMut, v:MatrixView, t:&Matrix, transpose:bool) {
+    let w = matrix_multiply_view(c.rb(), v).unwrap();
+    let t = if transpose { t.view().t() } else { t.view() };
+    let wt = matrix_multiply_view(w.view(), t).unwrap();
+    let update = matrix_multiply_view(wt.view(), v.t()).unwrap();
+    matrix_sub_assign(c, update.view());
+}
+
+// Leading ncol columns of H_0 H_1 ... H_{w-1} for reflectors stored column-wise below the
+// diagonal of a, w = tau.len(). Panels are applied last to first so each only touches the
+// rows and columns it can change.
+pub fn form_q(a:MatrixView, tau:&[f64], ncol:usize) -> Matrix {
+    let n = a.nrow;
+    let w = tau.len();
+    let mut q = Matrix::zeros(n, ncol);
+    for i in 0..min(n, ncol) {
+        q[(i, i)] = 1.0;
+    }
+
+    let starts:Vec<usize> = (0..w).step_by(QR_BLOCK).collect();
+
+    for &i in starts.iter().rev() {
+        let kb = min(QR_BLOCK, w-i);
+        let v = reflector_panel(a.subview(i..n, i..i+kb), kb);
+        let t = block_reflector_t(v.view(), &tau[i..i+kb]);
+        let c0 = min(i, ncol);
+        apply_block_reflector_left(q.view_mut().subview_mut(i..n, c0..ncol), v.view(), &t, false);
+    }
+
+    return q;
+}
 
-            q1 = matrix_multiply_simd_on_slices_left_vector(&u, &q_lt, n,  0, n1-1, i, n-1, 0, n-1);
+// Blocked Householder QR in LAPACK layout: on return R is on and above the diagonal of a and
+// the tails of the reflectors below it. Returns tau.
+#[allow(clippy::needless_range_loop)]
+pub fn householder_qr_in_place(a:&mut Matrix) -> Vec<f64> {
+    let (n, m) = a.shape();
+    let w = min(n, m);
+    let mut tau = vec![0.0;w];
 
-            for i1 in i..n {
-                mul_sub_const(&q1, &mut q_lt[i1*n..(i1+1)*n], 2.0*u[i1-i], n);
+    for i in (0..w).step_by(QR_BLOCK) {
+        let kb = min(QR_BLOCK, w-i);
+
+        for j in i..i+kb {
+            let mut x = a.view().subview(j..n, j..j+1).to_vec();
+            tau[j] = householder_reflector(&mut x);
+            a.view_mut().subview_mut(j..n, j..j+1).copy_from(MatrixView::new(&x, n-j, 1));
+
+            if tau[j] != 0.0 && j+1 < i+kb {
+                x[0] = 1.0;
+                apply_reflector_left(a.view_mut().subview_mut(j..n, j+1..i+kb), &x, tau[j]);
             }
         }
+
+        if i+kb < m {
+            let v = reflector_panel(a.view().subview(i..n, i..i+kb), kb);
+            let t = block_reflector_t(v.view(), &tau[i..i+kb]);
+            apply_block_reflector_left(a.view_mut().subview_mut(i..n, i+kb..m), v.view(), &t, true);
+        }
+    }
+
+    return tau;
+}
+
+pub fn householder_reflection_left_multiply(a:&Matrix) -> (Matrix, Matrix) {
+    let (n, m) = a.shape();
+    let mut r = a.clone();
+    let tau = householder_qr_in_place(&mut r);
+    let q_lt = form_q(r.view(), &tau, n).transpose();
+
+    for i in 0..n {
+        for j in 0..min(i, m) {
+            r[(i, j)] = 0.0;
+        }
     }
 
     return (q_lt, r);
 }
 
-pub fn householder_reflection_bidiagonalization(a:&[f64], n:usize, m:usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
-    let mut q_lt = identity(n);
-    let mut q_rt = identity(m);
+// One panel of blocked bidiagonalization (LAPACK's labrd) on the trailing block of a starting
+// at (p, p), for n >= m. The first nb columns and rows are reduced and X, Y are returned so
+// that the rest of the block can be updated as A -= VY' + XU' with two GEMMs. The diagonal
+// and superdiagonal of the panel are left holding 1 for the caller to use and restore.
+fn bidiagonal_panel(a:&mut Matrix, p:usize, nb:usize, d:&mut [f64], e:&mut [f64], tauq:&mut [f64], taup:&mut [f64]) -> (Matrix, Matrix) {
+    let (n, m) = a.shape();
+    let nn = n-p;
+    let mm = m-p;
+    let mut x = Matrix::zeros(nn, nb);
+    let mut y = Matrix::zeros(mm, nb);
+
+    for i in 0..nb {
+        let gi = p+i;
+
+        // Update column i with the previous left and right reflectors.
+        let yi = y.row(i)[0..i].to_vec();
+        let s1 = matrix_vector_multiply(a.view().subview(gi..n, p..gi), &yi).unwrap();
+        let ai = a.view().subview(p..gi, gi..gi+1).to_vec();
+        let s2 = matrix_vector_multiply(x.view().subview(i..nn, 0..i), &ai).unwrap();
+
+        let mut col = a.view().subview(gi..n, gi..gi+1).to_vec();
+        for r in 0..col.len() {
+            col[r] -= s1[r]+s2[r];
+        }
 
-    let mut r = a.to_vec();
+        tauq[gi] = householder_reflector(&mut col);
+        d[gi] = col[0];
+        col[0] = 1.0;
+        a.view_mut().subview_mut(gi..n, gi..gi+1).copy_from(MatrixView::new(&col, nn-i, 1));
+
+        if gi+1 < m {
+            let v = col;
+
+            let mut yc = vector_matrix_multiply(&v, a.view().subview(gi..n, gi+1..m)).unwrap();
+            let tmp = vector_matrix_multiply(&v, a.view().subview(gi..n, p..gi)).unwrap();
+            let s = matrix_vector_multiply(y.view().subview(i+1..mm, 0..i), &tmp).unwrap();
+            let tmp = vector_matrix_multiply(&v, x.view().subview(i..nn, 0..i)).unwrap();
+            let s2 = vector_matrix_multiply(&tmp, a.view().subview(p..gi, gi+1..m)).unwrap();
+            for r in 0..yc.len() {
+                yc[r] = tauq[gi]*(yc[r]-s[r]-s2[r]);
+            }
+            y.view_mut().subview_mut(i+1..mm, i..i+1).copy_from(MatrixView::new(&yc, mm-i-1, 1));
 
-    for i in 0..min(n, m) {
-        let n1 = n-i;
+            // Update row i with the previous reflectors, including the one just generated.
+            let arow = a.view().subview(gi..gi+1, p..gi+1).to_vec();
+            let s1 = matrix_vector_multiply(y.view().subview(i+1..mm, 0..i+1), &arow).unwrap();
+            let xi = x.row(i)[0..i].to_vec();
+            let s2 = vector_matrix_multiply(&xi, a.view().subview(p..gi, gi+1..m)).unwrap();
 
-        let mut nm = 0.0;
-        let mut u = vec![0.0;n1];
-        let mut r1;
-        let mut q1;
+            let mut row = a.view().subview(gi..gi+1, gi+1..m).to_vec();
+            for c in 0..row.len() {
+                row[c] -= s1[c]+s2[c];
+            }
 
-        for i1 in i..n {
-            nm += r[i1*m+i]*r[i1*m+i];
-            u[i1-i] = r[i1*m+i];
-        }
+            taup[gi] = householder_reflector(&mut row);
+            e[gi] = row[0];
+            row[0] = 1.0;
+            a.view_mut().subview_mut(gi..gi+1, gi+1..m).copy_from(MatrixView::new(&row, 1, mm-i-1));
 
-        u[0] -= sgn(r[i*m+i])*my_sqrt(nm);
-        let z = my_sqrt(nm-r[i*m+i]*r[i*m+i]+u[0]*u[0]);
+            let u = row;
 
-        if z > 0.0 {
-            for i1 in 0..n1 {
-                u[i1] = u[i1]/z;
+            let mut xc = matrix_vector_multiply(a.view().subview(gi+1..n, gi+1..m), &u).unwrap();
+            let tmp = vector_matrix_multiply(&u, y.view().subview(i+1..mm, 0..i+1)).unwrap();
+            let s = matrix_vector_multiply(a.view().subview(gi+1..n, p..gi+1), &tmp).unwrap();
+            let tmp = matrix_vector_multiply(a.view().subview(p..gi, gi+1..m), &u).unwrap();
+            let s2 = matrix_vector_multiply(x.view().subview(i+1..nn, 0..i), &tmp).unwrap();
+            for r in 0..xc.len() {
+                xc[r] = taup[gi]*(xc[r]-s[r]-s2[r]);
             }
+            x.view_mut().subview_mut(i+1..nn, i..i+1).copy_from(MatrixView::new(&xc, nn-i-1, 1));
+        }
+    }
 
-            r1 = matrix_multiply_simd_on_slices_left_vector(&u, &r, m,  0, n1-1, i, n-1, i, m-1);
+    return (x, y);
+}
+
+// Compact blocked bidiagonalization of a tall matrix, Q_L' A Q_R = B with B upper bidiagonal.
+// The left reflectors are stored below the diagonal of `reflectors` and the right ones to the
+// right of the superdiagonal, so Q_L and Q_R are only formed, and only as far, as asked for.
+#[derive(Clone, Debug)]
+pub struct HouseholderBidiagonal {
+    reflectors: Matrix,
+    d: Vec<f64>,
+    e: Vec<f64>,
+    tauq: Vec<f64>,
+    taup: Vec<f64>,
+}
 
-            for i1 in i..n {
-                mul_sub_const(&r1, &mut r[i1*m+i..(i1+1)*m], 2.0*u[i1-i], m-i);
+impl HouseholderBidiagonal {
+    pub fn new(a:&Matrix) -> Self {
+        let (n, m) = a.shape();
+        assert!(n >= m, "Cannot bidiagonalize {}x{} matrix, transpose it first", n, m);
+
+        let mut r = a.clone();
+        let mut d = vec![0.0;m];
+        let mut e = vec![0.0;m];
+        let mut tauq = vec![0.0;m];
+        let mut taup = vec![0.0;m];
+
+        for p in (0..m).step_by(QR_BLOCK) {
+            let nb = min(QR_BLOCK, m-p);
+            let (x, y) = bidiagonal_panel(&mut r, p, nb, &mut d, &mut e, &mut tauq, &mut taup);
+
+            if p+nb < m {
+                // A22 -= V Y' + X U'
+                let vy = matrix_multiply_view(r.view().subview(p+nb..n, p..p+nb), y.view().subview(nb..m-p, 0..nb).t()).unwrap();
+                let xu = matrix_multiply_view(x.view().subview(nb..n-p, 0..nb), r.view().subview(p..p+nb, p+nb..m)).unwrap();
+                matrix_sub_assign(r.view_mut().subview_mut(p+nb..n, p+nb..m), vy.view());
+                matrix_sub_assign(r.view_mut().subview_mut(p+nb..n, p+nb..m), xu.view());
             }
+        }
 
-            q1 = matrix_multiply_simd_on_slices_left_vector(&u, &q_lt, n,  0, n1-1, i, n-1, 0, n-1);
+        return Self {
+            reflectors: r,
+            d,
+            e,
+            tauq,
+            taup,
+        };
+    }
 
-            for i1 in i..n {
-                mul_sub_const(&q1, &mut q_lt[i1*n..(i1+1)*n], 2.0*u[i1-i], n);
+    // The m x m upper bidiagonal B.
+    pub fn b(&self) -> Matrix {
+        let m = self.d.len();
+        let mut b = Matrix::zeros(m, m);
+        for i in 0..m {
+            b[(i, i)] = self.d[i];
+            if i+1 < m {
+                b[(i, i+1)] = self.e[i];
             }
         }
+        return b;
+    }
 
-        if m-i-1 > 0 {
-            let n1 = m-i-1;
+    // Leading ncol columns of the n x n Q_L.
+    pub fn q_left(&self, ncol:usize) -> Matrix {
+        return form_q(self.reflectors.view(), &self.tauq, ncol);
+    }
 
-            nm = 0.0;
-            u = vec![0.0;n1];
+    // The m x m Q_R; its first row and column are e1.
+    pub fn q_right(&self) -> Matrix {
+        let m = self.d.len();
+        let mut q_rt = Matrix::identity(m);
+        if m > 1 {
+            let p1 = form_q(self.reflectors.view().subview(0..m-1, 1..m).t(), &self.taup[0..m-1], m-1);
+            q_rt.view_mut().subview_mut(1..m, 1..m).copy_from(p1.view());
+        }
+        return q_rt;
+    }
+}
 
-            for j1 in i+1..m {
-                nm += r[i*m+j1]*r[i*m+j1];
-                u[j1-i-1] = r[i*m+j1];
-            }
+fn householder_bidiagonalization_blocked(a:&Matrix) -> (Matrix, Matrix, Matrix) {
+    let (n, m) = a.shape();
+    let bd = HouseholderBidiagonal::new(a);
 
-            u[0] -= sgn(r[i*m+i+1])*my_sqrt(nm);
-            let z = my_sqrt(nm-r[i*m+i+1]*r[i*m+i+1]+u[0]*u[0]);
+    let mut b = Matrix::zeros(n, m);
+    b.view_mut().subview_mut(0..m, 0..m).copy_from(bd.b().view());
 
-            if z > 0.0 {
-                for i1 in 0..n1 {
-                    u[i1] = u[i1]/z;
-                }
+    return (bd.q_left(n).transpose(), b, bd.q_right());
+}
 
-                r1 = matrix_multiply_simd_on_slices_right_vector(&r, &u, m, i, n-1, i+1, m-1);
+// q_lt * a * q_rt is upper bidiagonal.
+pub fn householder_reflection_bidiagonalization(a:&Matrix) -> (Matrix, Matrix, Matrix) {
+    let (n, m) = a.shape();
 
-                for i1 in i..n {
-                    for j1 in i+1..m {
-                        r[i1*m+j1] -= 2.0*u[j1-i-1]*r1[i1-i];
-                    }
-                }
+    if n >= m {
+        return householder_bidiagonalization_blocked(a);
+    }
 
-                q1 = matrix_multiply_simd_on_slices_right_vector(&q_rt, &u, m, 0, m-1, i+1, m-1);
+    let mut q_lt = Matrix::identity(n);
+    let mut q_rt = Matrix::identity(m);
 
-                for i1 in 0..m {
-                    for j1 in i+1..m {
-                        q_rt[i1*m+j1] -= 2.0*u[j1-i-1]*q1[i1];
-                    }
-                }
+    let mut r = a.clone();
+
+    for i in 0..n {
+        let x = r.view().subview(i..n, i..i+1).to_vec();
+
+        if let Some(u) = householder_vector(&x) {
+            householder_left_multiply(r.view_mut().subview_mut(i..n, i..m), &u);
+            householder_left_multiply(q_lt.view_mut().subview_mut(i..n, 0..n), &u);
+        }
+
+        if i+1 < m {
+            let x = r.view().subview(i..i+1, i+1..m).to_vec();
+
+            if let Some(u) = householder_vector(&x) {
+                householder_right_multiply(r.view_mut().subview_mut(i..n, i+1..m), &u);
+                householder_right_multiply(q_rt.view_mut().subview_mut(0..m, i+1..m), &u);
             }
         }
     }
@@ -126,17 +413,12 @@ pub fn householder_reflection_bidiagonalization(a:&[f64], n:usize, m:usize) -> (
     return (q_lt, r, q_rt);
 }
 
-pub fn givens_right_rotation(a:&[f64], _n:usize, m:usize, i:usize, j:usize, flip:bool) -> (f64, f64) {
-    let x = a[i*m+j-1];
-    let y = a[i*m+j];
-    let w = x*x+y*y;
-    let r;
-
-    if w < 1e-100 {
-        r = hypot(x, y);
-    }
-    else {
-        r = my_sqrt(w);
+pub fn givens_right_rotation(a:MatrixView, i:usize, j:usize, flip:bool) -> (f64, f64) {
+    let x = a[(i, j-1)];
+    let y = a[(i, j)];
+    let r = x.hypot(y);
+    if r == 0.0 {
+        return (1.0, 0.0);
     }
 
     if flip {
@@ -146,26 +428,22 @@ pub fn givens_right_rotation(a:&[f64], _n:usize, m:usize, i:usize, j:usize, flip
     return (x/r, -y/r);
 }
 
-pub fn givens_right_rotation_multiply(a:&mut [f64], _n:usize, m:usize, c:f64, s:f64, _i:usize, j:usize, r1:usize, r2:usize, _c1:usize, _c2:usize) {
-    for i1 in r1..r2+1 {
-        let p = a[i1*m+j-1];
-        let q = a[i1*m+j];
-        a[i1*m+j-1] = c*p - s*q;
-        a[i1*m+j] = s*p + c*q;
+// Rotates columns j-1 and j across every row of the view.
+pub fn givens_right_rotation_multiply(mut a:MatrixViewMut, c:f64, s:f64, j:usize) {
+    for i1 in 0..a.nrow {
+        let p = a[(i1, j-1)];
+        let q = a[(i1, j)];
+        a[(i1, j-1)] = c*p - s*q;
+        a[(i1, j)] = s*p + c*q;
     }
 }
 
-pub fn givens_left_rotation(a:&[f64], _n:usize, m:usize, i:usize, j:usize, flip:bool) -> (f64, f64) {
-    let x = a[(i-1)*m+j];
-    let y = a[i*m+j];
-    let w = x*x+y*y;
-    let r;
-    
-    if w < 1e-100 {
-        r = hypot(x, y);
-    }
-    else {
-        r = my_sqrt(w);
+pub fn givens_left_rotation(a:MatrixView, i:usize, j:usize, flip:bool) -> (f64, f64) {
+    let x = a[(i-1, j)];
+    let y = a[(i, j)];
+    let r = x.hypot(y);
+    if r == 0.0 {
+        return (1.0, 0.0);
     }
 
     if flip {
@@ -175,11 +453,92 @@ pub fn givens_left_rotation(a:&[f64], _n:usize, m:usize, i:usize, j:usize, flip:
     return (x/r, -y/r);
 }
 
-pub fn givens_left_rotation_multiply(a:&mut [f64], _n:usize, m:usize, c:f64, s:f64, i:usize, _j:usize, _r1:usize, _r2:usize, c1:usize, c2:usize) {
-    for j1 in c1..c2+1 {
-        let p = a[(i-1)*m+j1];
-        let q = a[i*m+j1];
-        a[(i-1)*m+j1] = c*p - s*q;
-        a[i*m+j1] = s*p + c*q;
+// Rotates rows i-1 and i across every column of the view.
+pub fn givens_left_rotation_multiply(mut a:MatrixViewMut, c:f64, s:f64, i:usize) {
+    for j1 in 0..a.ncol {
+        let p = a[(i-1, j1)];
+        let q = a[(i, j1)];
+        a[(i-1, j1)] = c*p - s*q;
+        a[(i, j1)] = s*p + c*q;
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    fn check_bidiagonal(a:&Matrix) {
+        let (n, m) = a.shape();
+        let (q_lt, b, q_rt) = householder_reflection_bidiagonalization(a);
+        assert!(max_abs_diff(&q_lt.mul_tr(&q_lt), &Matrix::identity(n)) < 1e-12);
+        assert!(max_abs_diff(&q_rt.tr_mul(&q_rt), &Matrix::identity(m)) < 1e-12);
+        assert!(max_abs_diff(&(q_lt.clone()*a.clone()*q_rt.clone()), &b) < 1e-12);
+        for i in 0..n {
+            for j in 0..m {
+                if j != i && j != i+1 {
+                    assert!(b[(i, j)].abs() < 1e-12, "({}, {}) of {}x{}", i, j, n, m);
+                }
+            }
+        }
+        assert!(max_abs_diff(&(q_lt.tr_mul(&b)*q_rt.transpose()), a) < 1e-12);
+    }
+
+    #[test]
+    fn compact_wy_matches_product_of_reflectors() {
+        let n = 9;
+        let mut a = random(n, 4, 1);
+        let tau = householder_qr_in_place(&mut a);
+        let v = reflector_panel(a.view(), 4);
+        let t = block_reflector_t(v.view(), &tau);
+
+        let mut h = Matrix::identity(n);
+        for (j, &tj) in tau.iter().enumerate() {
+            apply_reflector_right(h.view_mut(), &v.column(j), tj);
+        }
+
+        let mut wy = Matrix::identity(n);
+        apply_block_reflector_left(wy.view_mut(), v.view(), &t, false);
+        assert!(max_abs_diff(&wy, &h) < 1e-12);
+
+        let mut wy = Matrix::identity(n);
+        apply_block_reflector_right(wy.view_mut(), v.view(), &t, true);
+        assert!(max_abs_diff(&wy, &h.transpose()) < 1e-12);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn blocked_qr_spans_several_panels() {
+        let (n, m) = (3*QR_BLOCK+5, 2*QR_BLOCK+7);
+        let a = random(n, m, 2);
+        let (q_lt, r) = householder_reflection_left_multiply(&a);
+        assert!(max_abs_diff(&q_lt.tr_mul(&r), &a) < 1e-11);
+        assert!(max_abs_diff(&q_lt.mul_tr(&q_lt), &Matrix::identity(n)) < 1e-12);
+        for i in 0..n {
+            for j in 0..min(i, m) {
+                assert_eq!(r[(i, j)], 0.0);
+            }
+        }
+
+        let mut b = a.clone();
+        let tau = householder_qr_in_place(&mut b);
+        let thin = form_q(b.view(), &tau, m);
+        assert!(max_abs_diff(&thin, &q_lt.transpose().sub_mat(0, n-1, 0, m-1)) < 1e-12);
+    }
+
+    #[test]
+    fn bidiagonalization_tall_wide_and_blocked() {
+        check_bidiagonal(&random(7, 5, 3));
+        check_bidiagonal(&random(5, 7, 4));
+        check_bidiagonal(&random(1, 1, 5));
+        check_bidiagonal(&random(2*QR_BLOCK+3, QR_BLOCK+9, 6));
+    }
+}
@@ -1,47 +1,366 @@
-#![allow(dead_code)]
-use crate::matrix_utils::*;
+#![allow(dead_code, clippy::needless_return)]
+use crate::dense_matrix::Matrix;
 use crate::orthogonal_matrices::*;
+use crate::matrix_utils::*;
+use crate::matrix_view::MatrixView;
+use crate::linalg_error::*;
+use crate::row_echelon::swap_cols;
+use std::cmp::min;
+
+pub fn givens_rotation_qr(a:&Matrix) -> (Matrix, Matrix) {
+    let (n, m) = a.shape();
 
-pub fn givens_rotation_qr(a:&[f64], n:usize, m:usize,) -> (Vec<f64>, Vec<f64>, usize, usize, usize) {
     if n < m {
-        let mut r = transpose(&a, n, m);
-        let mut q = identity(m);
+        let mut r = a.transpose();
+        let mut q = Matrix::identity(m);
 
         for j in 0..n {
             for i in (j+1..m).rev() {
-                let b = givens_left_rotation(&r, m, n, i, j, false);
-                givens_left_rotation_multiply(&mut r, m, n, b.0, b.1, i, j, 0, m-1, 0, n-1);
-                givens_left_rotation_multiply(&mut q, m, m, b.0, b.1, i, j, 0, m-1, 0, m-1);
+                let b = givens_left_rotation(r.view(), i, j, false);
+                givens_left_rotation_multiply(r.view_mut(), b.0, b.1, i);
+                givens_left_rotation_multiply(q.view_mut(), b.0, b.1, i);
             }
         }
 
-        return (transpose(&r, m, n), q, n, m, m);
+        return (r.transpose(), q);
     }
     else {
-        let mut r = a.to_vec();
-        let mut q = identity(n);
+        let mut r = a.clone();
+        let mut q = Matrix::identity(n);
 
         for j in 0..m {
             for i in (j+1..n).rev() {
-                let b = givens_left_rotation(&r, n, m, i, j, false);
-                givens_left_rotation_multiply(&mut r, n, m, b.0, b.1, i, j, 0, n-1, 0, m-1);
-                givens_left_rotation_multiply(&mut q, n, n, b.0, b.1, i, j, 0, n-1, 0, n-1);
+                let b = givens_left_rotation(r.view(), i, j, false);
+                givens_left_rotation_multiply(r.view_mut(), b.0, b.1, i);
+                givens_left_rotation_multiply(q.view_mut(), b.0, b.1, i);
             }
         }
 
-        return (transpose(&q, n, n), r, n, n, m);
+        return (q.transpose(), r);
     }
-    
+
 }
 
-pub fn householder_reflection_qr(a:&[f64], n:usize, m:usize) -> (Vec<f64>, Vec<f64>, usize, usize, usize) {
+pub fn householder_reflection_qr(a:&Matrix) -> (Matrix, Matrix) {
+    let (n, m) = a.shape();
+
     if n < m {
-        let a1 = transpose(&a, n, m);
-        let (q, r) = householder_reflection_left_multiply(&a1, m, n);
-        return (transpose(&r, m, n), q, n, m, m);
+        let (q, r) = householder_reflection_left_multiply(&a.transpose());
+        return (r.transpose(), q);
     }
     else {
-        let (q, r) = householder_reflection_left_multiply(&a, n, m);
-        return (transpose(&q, n, n), r, n, n, m);
+        let (q, r) = householder_reflection_left_multiply(a);
+        return (q.transpose(), r);
+    }
+}
+
+// AP = QR with Businger-Golub column pivoting: at every step the remaining column of largest
+// norm is moved to the front, so |r_00| >= |r_11| >= ... and the trailing diagonal of R
+// reveals the numerical rank. Column j of AP is column perm[j] of A, so perm[0..rank] picks a
+// well-conditioned set of independent columns. Q is kept as reflectors in `qr`, so callers form
+// only as much of it as they need.
+#[derive(Clone, Debug)]
+pub struct PivotedQr {
+    pub qr: HouseholderQr,
+    pub perm: Vec<usize>,
+    // Number of diagonal entries of R above tol*|r_00|.
+    pub rank: usize,
+}
+
+// When tol is None it defaults to max(n, m)*eps.
+pub fn column_pivoted_qr(a:&Matrix, tol:Option<f64>) -> Result<PivotedQr, LinalgError> {
+    check_finite(a.as_slice())?;
+
+    let (n, m) = a.shape();
+    let w = min(n, m);
+    let tol = tol.unwrap_or((n.max(m) as f64)*f64::EPSILON);
+
+    let mut r = a.clone();
+    let mut tau = vec![0.0;w];
+    let mut perm:Vec<usize> = (0..m).collect();
+
+    let col_norm = |r:&Matrix, i:usize, j:usize| -> f64 {
+        return norm(&r.view().subview(i..n, j..j+1).to_vec(), n-i);
+    };
+
+    let mut norms:Vec<f64> = (0..m).map(|j| col_norm(&r, 0, j)).collect();
+    let mut exact = norms.clone();
+
+    for i in 0..w {
+        let mut p = i;
+        for j in i+1..m {
+            if norms[j] > norms[p] {
+                p = j;
+            }
+        }
+
+        if p != i {
+            swap_cols(r.as_mut_slice(), n, m, i, p);
+            perm.swap(i, p);
+            norms.swap(i, p);
+            exact.swap(i, p);
+        }
+
+        let mut x = r.view().subview(i..n, i..i+1).to_vec();
+        tau[i] = householder_reflector(&mut x);
+        r.view_mut().subview_mut(i..n, i..i+1).copy_from(MatrixView::new(&x, n-i, 1));
+
+        if tau[i] != 0.0 && i+1 < m {
+            x[0] = 1.0;
+            apply_reflector_left(r.view_mut().subview_mut(i..n, i+1..m), &x, tau[i]);
+        }
+
+        // Downdate the remaining column norms, recomputing any that lost too many digits.
+        for j in i+1..m {
+            if norms[j] == 0.0 {
+                continue;
+            }
+            let t = (r[(i, j)].abs()/norms[j]).powi(2);
+            let t = (1.0-t).max(0.0);
+            let ratio = norms[j]/exact[j];
+
+            if t*ratio*ratio <= f64::EPSILON.sqrt() {
+                norms[j] = if i+1 < n { col_norm(&r, i+1, j) } else { 0.0 };
+                exact[j] = norms[j];
+            }
+            else {
+                norms[j] *= t.sqrt();
+            }
+        }
+    }
+
+    let r00 = if w > 0 { r[(0, 0)].abs() } else { 0.0 };
+    let rank = (0..w).filter(|&i| r[(i, i)].abs() > tol*r00).count();
+
+    return Ok(PivotedQr {
+        qr: HouseholderQr {
+            qr: r,
+            tau,
+        },
+        perm,
+        rank,
+    });
+}
+
+// Compact Householder QR: A = QR with Q = H_0 H_1 ... H_{w-1}, H_i = I - tau_i v_i v_i'. R sits
+// on and above the diagonal of `qr` and the tail of each v_i below it (v_i[i] = 1 implicitly),
+// so Q is never formed unless asked for.
+#[derive(Clone, Debug)]
+pub struct HouseholderQr {
+    qr: Matrix,
+    tau: Vec<f64>,
+}
+
+impl HouseholderQr {
+    pub fn new(a:&Matrix) -> Self {
+        let mut qr = a.clone();
+        let tau = householder_qr_in_place(&mut qr);
+
+        return Self {
+            qr,
+            tau,
+        };
+    }
+
+    pub fn reflectors(&self) -> &Matrix {
+        return &self.qr;
+    }
+
+    pub fn tau(&self) -> &[f64] {
+        return &self.tau;
+    }
+
+    // Upper trapezoidal min(n, m) x m factor of the economy QR.
+    pub fn r(&self) -> Matrix {
+        let (n, m) = self.qr.shape();
+        let w = min(n, m);
+        let mut r = Matrix::zeros(w, m);
+        for i in 0..w {
+            for j in i..m {
+                r[(i, j)] = self.qr[(i, j)];
+            }
+        }
+        return r;
+    }
+
+    fn apply(&self, b:&mut Matrix, transpose:bool) {
+        let n = self.qr.nrow;
+        assert!(b.nrow == n, "Cannot apply {}x{} Q to {}x{} matrix", n, n, b.nrow, b.ncol);
+        let k = b.ncol;
+        let w = self.tau.len();
+
+        let mut starts:Vec<usize> = (0..w).step_by(QR_BLOCK).collect();
+        if !transpose {
+            starts.reverse();
+        }
+
+        for i in starts {
+            let kb = min(QR_BLOCK, w-i);
+            let v = reflector_panel(self.qr.view().subview(i..n, i..i+kb), kb);
+            let t = block_reflector_t(v.view(), &self.tau[i..i+kb]);
+            apply_block_reflector_left(b.view_mut().subview_mut(i..n, 0..k), v.view(), &t, transpose);
+        }
+    }
+
+    // B <- QB for an n x k matrix B.
+    pub fn apply_q(&self, b:&mut Matrix) {
+        self.apply(b, false);
+    }
+
+    // B <- Q'B for an n x k matrix B.
+    pub fn apply_qt(&self, b:&mut Matrix) {
+        self.apply(b, true);
+    }
+
+    // Leading min(n, m) columns of Q.
+    pub fn thin_q(&self) -> Matrix {
+        return form_q(self.qr.view(), &self.tau, self.tau.len());
     }
-}
\ No newline at end of file
+
+    pub fn q(&self) -> Matrix {
+        return form_q(self.qr.view(), &self.tau, self.qr.nrow);
+    }
+}
+
+// Economy QR: A = QR with Q n x min(n, m) having orthonormal columns and R min(n, m) x m.
+pub fn thin_qr(a:&Matrix) -> (Matrix, Matrix) {
+    let qr = HouseholderQr::new(a);
+    return (qr.thin_q(), qr.r());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    fn permute_cols(a:&Matrix, perm:&[usize]) -> Matrix {
+        let mut out = Matrix::zeros(a.nrow, a.ncol);
+        for i in 0..a.nrow {
+            for j in 0..a.ncol {
+                out[(i, j)] = a[(i, perm[j])];
+            }
+        }
+        return out;
+    }
+
+    fn check_pivoted(a:&Matrix, qrcp:&PivotedQr) {
+        let (n, m) = a.shape();
+        let q = qrcp.qr.thin_q();
+        let r = qrcp.qr.r();
+        assert_eq!(q.shape(), (n, min(n, m)));
+        assert!(max_abs_diff(&(q.clone()*r.clone()), &permute_cols(a, &qrcp.perm)) < 1e-12);
+        assert!(max_abs_diff(&q.tr_mul(&q), &Matrix::identity(min(n, m))) < 1e-12);
+        let full = qrcp.qr.q();
+        assert!(max_abs_diff(&full.tr_mul(&full), &Matrix::identity(n)) < 1e-12);
+        let d:Vec<f64> = r.diag().iter().map(|x| x.abs()).collect();
+        assert!(d.windows(2).all(|w| w[0] >= w[1]-1e-12), "{:?}", d);
+    }
+
+    #[test]
+    fn householder_qr_reconstructs_tall_and_wide() {
+        for &(n, m) in &[(8, 5), (5, 8), (6, 6)] {
+            let a = random(n, m, 1);
+            // Wide input comes back as A = LQ, so the orthogonal factor is the second one.
+            let (f1, f2) = householder_reflection_qr(&a);
+            assert!(max_abs_diff(&(f1.clone()*f2.clone()), &a) < 1e-12);
+            let q = if n < m { f2 } else { f1 };
+            assert!(max_abs_diff(&q.tr_mul(&q), &Matrix::identity(q.ncol)) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn pivoted_qr_of_full_rank_matrix() {
+        for &(n, m) in &[(9, 6), (6, 9)] {
+            let a = random(n, m, 2);
+            let qr = column_pivoted_qr(&a, None).unwrap();
+            assert_eq!(qr.rank, min(n, m));
+            check_pivoted(&a, &qr);
+        }
+    }
+
+    #[test]
+    fn pivoted_qr_reveals_rank() {
+        let a = random(12, 3, 3)*random(3, 8, 4);
+        let qr = column_pivoted_qr(&a, None).unwrap();
+        assert_eq!(qr.rank, 3);
+        check_pivoted(&a, &qr);
+        for i in 3..8 {
+            assert!(qr.qr.r()[(i, i)].abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn pivoted_qr_picks_dominant_column_first() {
+        let a = Matrix::new(3, 3, vec![1.0, 0.0, 10.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0]);
+        let qr = column_pivoted_qr(&a, None).unwrap();
+        assert_eq!(qr.perm[0], 2);
+        assert_eq!(qr.rank, 2);
+        assert!((qr.qr.r()[(0, 0)].abs()-10.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pivoted_qr_rejects_non_finite_input() {
+        let a = Matrix::new(2, 2, vec![1.0, f64::NAN, 0.0, 1.0]);
+        assert!(matches!(column_pivoted_qr(&a, None), Err(LinalgError::NonFinite)));
+    }
+
+    #[test]
+    fn apply_q_and_apply_qt_match_explicit_q() {
+        let a = random(10, 6, 5);
+        let qr = HouseholderQr::new(&a);
+        let q = qr.q();
+        assert!(max_abs_diff(&q.tr_mul(&q), &Matrix::identity(10)) < 1e-12);
+
+        let b = random(10, 3, 6);
+        let mut qb = b.clone();
+        qr.apply_q(&mut qb);
+        assert!(max_abs_diff(&qb, &(q.clone()*b.clone())) < 1e-12);
+
+        let mut qtb = b.clone();
+        qr.apply_qt(&mut qtb);
+        assert!(max_abs_diff(&qtb, &q.tr_mul(&b)) < 1e-12);
+
+        qr.apply_q(&mut qtb);
+        assert!(max_abs_diff(&qtb, &b) < 1e-12);
+    }
+
+    #[test]
+    fn thin_qr_reconstructs_tall_and_wide() {
+        for &(n, m) in &[(12, 4), (4, 7), (5, 5)] {
+            let a = random(n, m, 7);
+            let (q, r) = thin_qr(&a);
+            let w = min(n, m);
+            assert_eq!(q.shape(), (n, w));
+            assert_eq!(r.shape(), (w, m));
+            assert!(max_abs_diff(&(q.clone()*r.clone()), &a) < 1e-12);
+            assert!(max_abs_diff(&q.tr_mul(&q), &Matrix::identity(w)) < 1e-12);
+            for i in 0..w {
+                for j in 0..i {
+                    assert_eq!(r[(i, j)], 0.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reflector_skips_multiples_of_e1() {
+        let mut x = vec![-3.0, 0.0, 0.0];
+        assert_eq!(householder_reflector(&mut x), 0.0);
+        assert_eq!(x, vec![-3.0, 0.0, 0.0]);
+
+        let mut x = vec![3.0, 4.0];
+        let tau = householder_reflector(&mut x);
+        assert!((x[0]+5.0).abs() < 1e-12);
+        assert!(tau > 0.0 && tau <= 2.0);
+    }
+}
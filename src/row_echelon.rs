@@ -1,7 +1,5 @@
 #![allow(dead_code)]
 use std::simd::prelude::*;
-use rand_distr::{Distribution, Normal};
-use rand::thread_rng;
 
 pub fn swap_rows(inp:&mut [f64], m:usize, p:usize, q:usize) {
     const LANES:usize = 64;
@@ -9,9 +7,7 @@ pub fn swap_rows(inp:&mut [f64], m:usize, p:usize, q:usize) {
     for j in (0..m).step_by(LANES) {
         if p*m+j+LANES > (p+1)*m {
             for k in j..m {
-                let temp = inp[p*m+k];
-                inp[p*m+k] = inp[q*m+k];
-                inp[q*m+k] = temp;
+                inp.swap(p*m+k, q*m+k);
             }
             break;
         }
@@ -25,6 +21,12 @@ pub fn swap_rows(inp:&mut [f64], m:usize, p:usize, q:usize) {
     }
 }
 
+pub fn swap_cols(inp:&mut [f64], n:usize, m:usize, p:usize, q:usize) {
+    for i in 0..n {
+        inp.swap(i*m+p, i*m+q);
+    }
+}
+
 pub fn normalize_row(inp:&mut [f64], h:f64, m:usize, p:usize) {
     const LANES:usize = 64;
     let x:Simd<f64, LANES> = Simd::splat(1.0/h);
@@ -32,7 +34,7 @@ pub fn normalize_row(inp:&mut [f64], h:f64, m:usize, p:usize) {
     for j in (0..m).step_by(LANES) {
         if p*m+j+LANES > (p+1)*m {
             for k in j..m {
-                inp[p*m+k] = inp[p*m+k]/h;
+                inp[p*m+k] /= h;
             }
             break;
         }
@@ -51,7 +53,7 @@ pub fn reduce_row(inp:&mut [f64], m:usize, p:usize, q:usize, h:f64) {
     for j in (0..m).step_by(LANES) {
         if p*m+j+LANES > (p+1)*m {
             for k in j..m {
-                inp[q*m+k] = inp[q*m+k] - h*inp[p*m+k];
+                inp[q*m+k] -= h*inp[p*m+k];
             }
             break;
         }
@@ -85,22 +87,29 @@ pub fn row_echelon(inp:&mut [f64], n:usize, m:usize) {
     }
 }
 
-pub fn run() {
-    let n = 10;
-    let m = 5;
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    let mut mat:Vec<f64> = vec![0.0;n*m];
-    for i in 0..n*m {
-        mat[i] = normal.sample(&mut rng);
-    }
+pub fn reduced_row_echelon(inp:&mut [f64], n:usize, m:usize) {
+    for j in 0..m {
+        for i in j..n {
+            if inp[i*m + j] != 0.0 {
+                let h = inp[i*m+j];
+                if i == j {
+                    normalize_row(inp, h, m, j);
+                }
+                else if i > j && inp[j*m+j] != 1.0 {
+                    swap_rows(inp, m, j, i);
+                    normalize_row(inp, h, m, j);
+                }
+                else {
+                    reduce_row(inp, m, j, i, h);
+                }
+            }
+        }
 
-    row_echelon(&mut mat, n, m);
-    
-    for i in 0..n {
-        for j in 0..m {
-            print!("{:?} ", mat[i*m+j]);
+        for i in 0..j {
+            if inp[j*m+j] == 1.0 && inp[i*m + j] != 0.0 {
+                let h = inp[i*m+j];
+                reduce_row(inp, m, j, i, h);
+            }
         }
-        println!();
     }
 }
@@ -1,11 +1,9 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::needless_return)]
 use std::cmp::min;
-use rand_distr::{Distribution, Normal, Uniform};
-use rand::thread_rng;
-use std::time::SystemTime;
 use std::thread;
 use std::sync::{Arc, Mutex};
 use std::simd::prelude::*;
+use crate::linalg_error::LinalgError;
 
 fn binary_search(arr:&[usize], i:usize) -> usize {
     let n = arr.len();
@@ -92,7 +90,7 @@ impl SparseMatrix {
     pub fn create(
         nrow: usize,
         ncol: usize,
-        dense_data: &Vec<f64>
+        dense_data: &[f64]
     ) -> Self {
 
         let mut keys:Vec<usize> = Vec::new();
@@ -126,187 +124,183 @@ pub fn loc(a:&SparseMatrix, i:usize, j:usize) -> Option<f64> {
     return None;
 }
 
-pub fn get_sub_mat(a:&SparseMatrix, r_start:usize, r_end:usize, c_start:usize, c_end:usize) -> SparseMatrix {
+pub fn get_sub_mat(a:&SparseMatrix, r_start:usize, r_end:usize, c_start:usize, c_end:usize) -> Result<SparseMatrix, LinalgError> {
     let mut keys:Vec<usize> = Vec::new();
     let mut data:Vec<f64> = Vec::new();
 
-    let mut n = 0;
-    let mut m = 0;
+    if r_start > r_end || c_start > c_end || r_end >= a.nrow || c_end >= a.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (a.nrow, a.ncol), found: (r_end+1, c_end+1) });
+    }
 
-    if a.nrow >= r_end-r_start+1 && a.ncol >= c_end-c_start+1 {
-        let k_start = r_start*a.ncol + c_start;
-        let mut h = binary_search_next(&a.keys, k_start);
+    let n = r_end-r_start+1;
+    let m = c_end-c_start+1;
 
-        n = r_end-r_start+1;
-        m = c_end-c_start+1;
+    let k_start = r_start*a.ncol + c_start;
+    let mut h = binary_search_next(&a.keys, k_start);
 
-        while h < a.keys.len() {
-            let key = a.keys[h];
-            let d = a.data[h];
+    while h < a.keys.len() {
+        let key = a.keys[h];
+        let d = a.data[h];
 
-            let i = key/a.ncol;
-            let j = key % a.ncol;
+        let i = key/a.ncol;
+        let j = key % a.ncol;
 
-            if i > r_end {
-                break;
-            }
+        if i > r_end {
+            break;
+        }
 
-            if j >= c_start && j <= c_end {
-                keys.push((i-r_start)*m+(j-c_start));
-                data.push(d);
-                h += 1;
-            }
+        if j >= c_start && j <= c_end {
+            keys.push((i-r_start)*m+(j-c_start));
+            data.push(d);
+            h += 1;
+        }
 
-            else if j < c_start{
-                let k_start = i*a.ncol + c_start;
-                h = binary_search_next(&a.keys, k_start);
-            }
+        else if j < c_start{
+            let k_start = i*a.ncol + c_start;
+            h = binary_search_next(&a.keys, k_start);
+        }
 
-            else {
-                let k_start = (i+1)*a.ncol + c_start;
-                h = binary_search_next(&a.keys, k_start);
-            }
+        else {
+            let k_start = (i+1)*a.ncol + c_start;
+            h = binary_search_next(&a.keys, k_start);
         }
     }
 
-    return SparseMatrix::new(n, m, keys, data);
+    return Ok(SparseMatrix::new(n, m, keys, data));
 }
 
-pub fn add(a:&SparseMatrix, b:&SparseMatrix) -> SparseMatrix {
+pub fn add(a:&SparseMatrix, b:&SparseMatrix) -> Result<SparseMatrix, LinalgError> {
     let mut keys:Vec<usize> = Vec::new();
     let mut data:Vec<f64> = Vec::new();
 
-    let mut n = 0;
-    let mut m = 0;
+    if a.nrow != b.nrow || a.ncol != b.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (a.nrow, a.ncol), found: (b.nrow, b.ncol) });
+    }
 
-    if a.nrow > 0 && a.ncol > 0 && a.nrow == b.nrow && a.ncol == b.ncol {
-        n = a.nrow;
-        m = a.ncol;
+    let n = a.nrow;
+    let m = a.ncol;
 
-        let mut i:usize = 0;
-        let mut j:usize = 0;
+    let mut i:usize = 0;
+    let mut j:usize = 0;
 
-        while i < a.keys.len() && j < b.keys.len() {
-            if a.keys[i] < b.keys[j] {
-                keys.push(a.keys[i]);
-                data.push(a.data[i]);
-                i += 1;
-            }
-            else if a.keys[i] > b.keys[j] {
-                keys.push(b.keys[j]);
-                data.push(b.data[j]);
-                j += 1;
-            }
-            else {
-                if (a.data[i]+b.data[j]).abs() > 1e-10 {
-                    keys.push(a.keys[i]);
-                    data.push(a.data[i]+b.data[j]);
-                }
-                i += 1;
-                j += 1;
-            }
-        }
-
-        while i < a.keys.len() {
+    while i < a.keys.len() && j < b.keys.len() {
+        if a.keys[i] < b.keys[j] {
             keys.push(a.keys[i]);
             data.push(a.data[i]);
             i += 1;
         }
-
-        while j < b.keys.len() {
+        else if a.keys[i] > b.keys[j] {
             keys.push(b.keys[j]);
             data.push(b.data[j]);
             j += 1;
         }
+        else {
+            if (a.data[i]+b.data[j]).abs() > 1e-10 {
+                keys.push(a.keys[i]);
+                data.push(a.data[i]+b.data[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+
+    while i < a.keys.len() {
+        keys.push(a.keys[i]);
+        data.push(a.data[i]);
+        i += 1;
+    }
+
+    while j < b.keys.len() {
+        keys.push(b.keys[j]);
+        data.push(b.data[j]);
+        j += 1;
     }
     
-    return SparseMatrix::new(n, m, keys, data);
+    return Ok(SparseMatrix::new(n, m, keys, data));
 }
 
-pub fn sub(a:&SparseMatrix, b:&SparseMatrix) -> SparseMatrix {
+pub fn sub(a:&SparseMatrix, b:&SparseMatrix) -> Result<SparseMatrix, LinalgError> {
     let mut keys:Vec<usize> = Vec::new();
     let mut data:Vec<f64> = Vec::new();
 
-    let mut n = 0;
-    let mut m = 0;
-
-    if a.nrow > 0 && a.ncol > 0 && a.nrow == b.nrow && a.ncol == b.ncol {
-        n = a.nrow;
-        m = a.ncol;
+    if a.nrow != b.nrow || a.ncol != b.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (a.nrow, a.ncol), found: (b.nrow, b.ncol) });
+    }
 
-        let mut i:usize = 0;
-        let mut j:usize = 0;
+    let n = a.nrow;
+    let m = a.ncol;
 
-        while i < a.keys.len() && j < b.keys.len() {
-            if a.keys[i] < b.keys[j] {
-                keys.push(a.keys[i]);
-                data.push(a.data[i]);
-                i += 1;
-            }
-            else if a.keys[i] > b.keys[j] {
-                keys.push(b.keys[j]);
-                data.push(-b.data[j]);
-                j += 1;
-            }
-            else {
-                if (a.data[i]-b.data[j]).abs() > 1e-10 {
-                    keys.push(a.keys[i]);
-                    data.push(a.data[i]-b.data[j]);
-                }
-                i += 1;
-                j += 1;
-            }
-        }
+    let mut i:usize = 0;
+    let mut j:usize = 0;
 
-        while i < a.keys.len() {
+    while i < a.keys.len() && j < b.keys.len() {
+        if a.keys[i] < b.keys[j] {
             keys.push(a.keys[i]);
             data.push(a.data[i]);
             i += 1;
         }
-
-        while j < b.keys.len() {
+        else if a.keys[i] > b.keys[j] {
             keys.push(b.keys[j]);
             data.push(-b.data[j]);
             j += 1;
         }
+        else {
+            if (a.data[i]-b.data[j]).abs() > 1e-10 {
+                keys.push(a.keys[i]);
+                data.push(a.data[i]-b.data[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+
+    while i < a.keys.len() {
+        keys.push(a.keys[i]);
+        data.push(a.data[i]);
+        i += 1;
+    }
+
+    while j < b.keys.len() {
+        keys.push(b.keys[j]);
+        data.push(-b.data[j]);
+        j += 1;
     }
     
-    return SparseMatrix::new(n, m, keys, data);
+    return Ok(SparseMatrix::new(n, m, keys, data));
 }
 
-pub fn mul(a:&SparseMatrix, b:&SparseMatrix) -> SparseMatrix {
+pub fn mul(a:&SparseMatrix, b:&SparseMatrix) -> Result<SparseMatrix, LinalgError> {
     let mut keys:Vec<usize> = Vec::new();
     let mut data:Vec<f64> = Vec::new();
 
-    let mut n = 0;
-    let mut m = 0;
+    if a.nrow != b.nrow || a.ncol != b.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (a.nrow, a.ncol), found: (b.nrow, b.ncol) });
+    }
 
-    if a.nrow > 0 && a.ncol > 0 && a.nrow == b.nrow && a.ncol == b.ncol {
-        n = a.nrow;
-        m = a.ncol;
+    let n = a.nrow;
+    let m = a.ncol;
 
-        let mut i:usize = 0;
-        let mut j:usize = 0;
+    let mut i:usize = 0;
+    let mut j:usize = 0;
 
-        while i < a.keys.len() && j < b.keys.len() {
-            if a.keys[i] < b.keys[j] {
-                i += 1;
-            }
-            else if a.keys[i] > b.keys[j] {
-                j += 1;
-            }
-            else {
-                if (a.data[i]*b.data[j]).abs() > 1e-10 {
-                    keys.push(a.keys[i]);
-                    data.push(a.data[i]*b.data[j]);
-                }
-                i += 1;
-                j += 1;
+    while i < a.keys.len() && j < b.keys.len() {
+        if a.keys[i] < b.keys[j] {
+            i += 1;
+        }
+        else if a.keys[i] > b.keys[j] {
+            j += 1;
+        }
+        else {
+            if (a.data[i]*b.data[j]).abs() > 1e-10 {
+                keys.push(a.keys[i]);
+                data.push(a.data[i]*b.data[j]);
             }
+            i += 1;
+            j += 1;
         }
     }
     
-    return SparseMatrix::new(n, m, keys, data);
+    return Ok(SparseMatrix::new(n, m, keys, data));
 }
 
 pub fn mul_const(a:&SparseMatrix, b:f64) -> SparseMatrix {
@@ -323,6 +317,36 @@ pub fn mul_const(a:&SparseMatrix, b:f64) -> SparseMatrix {
     return SparseMatrix::new(a.nrow, a.ncol, keys, data);
 }
 
+// y = Ax in one pass over the stored entries.
+pub fn mul_vec(a:&SparseMatrix, x:&[f64]) -> Result<Vec<f64>, LinalgError> {
+    if x.len() != a.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (a.ncol, 1), found: (x.len(), 1) });
+    }
+
+    let mut out:Vec<f64> = vec![0.0;a.nrow];
+    for i in 0..a.keys.len() {
+        let key = a.keys[i];
+        out[key/a.ncol] += a.data[i]*x[key % a.ncol];
+    }
+
+    return Ok(out);
+}
+
+// y = A'x without forming the transpose.
+pub fn tr_mul_vec(a:&SparseMatrix, x:&[f64]) -> Result<Vec<f64>, LinalgError> {
+    if x.len() != a.nrow {
+        return Err(LinalgError::DimensionMismatch { expected: (a.nrow, 1), found: (x.len(), 1) });
+    }
+
+    let mut out:Vec<f64> = vec![0.0;a.ncol];
+    for i in 0..a.keys.len() {
+        let key = a.keys[i];
+        out[key % a.ncol] += a.data[i]*x[key/a.ncol];
+    }
+
+    return Ok(out);
+}
+
 pub fn transpose(a:&SparseMatrix) -> SparseMatrix {
     let mut keys:Vec<usize> = Vec::new();
     let mut data:Vec<f64> = Vec::new();
@@ -347,131 +371,130 @@ pub fn transpose(a:&SparseMatrix) -> SparseMatrix {
     return SparseMatrix::new(a.ncol, a.nrow, keys, data);
 }
 
-pub fn copy(a:&SparseMatrix, b:&SparseMatrix, r_start:usize, r_end:usize, c_start:usize, c_end:usize) -> SparseMatrix {
+pub fn copy(a:&SparseMatrix, b:&SparseMatrix, r_start:usize, r_end:usize, c_start:usize, c_end:usize) -> Result<SparseMatrix, LinalgError> {
     let mut keys:Vec<usize> = Vec::new();
     let mut data:Vec<f64> = Vec::new();
 
-    let mut n = 0;
-    let mut m = 0;
-
-    if a.nrow >= b.nrow && a.ncol >= b.ncol {
-        n = a.nrow;
-        m = a.ncol;
-
-        let mut b_keys:Vec<usize> = Vec::new();
-        let mut b_data:Vec<f64> = Vec::new();
+    if r_end < r_start || c_end < c_start || r_end >= a.nrow || c_end >= a.ncol || b.nrow != r_end-r_start+1 || b.ncol != c_end-c_start+1 {
+        return Err(LinalgError::DimensionMismatch { expected: (r_end+1-r_start, c_end+1-c_start), found: (b.nrow, b.ncol) });
+    }
 
-        for i in 0..b.keys.len() {
-            let key = b.keys[i];
-            let r = key/b.ncol;
-            let c = key % b.ncol;
-            let nkey = (r+r_start)*a.ncol+(c+c_start);
-            b_keys.push(nkey);
-            b_data.push(b.data[i]);
-        }
+    let n = a.nrow;
+    let m = a.ncol;
 
-        let mut i:usize = 0;
-        let mut j:usize = 0;
+    let mut b_keys:Vec<usize> = Vec::new();
+    let mut b_data:Vec<f64> = Vec::new();
 
-        while i < a.keys.len() && j < b_keys.len() {
-            if a.keys[i] < b_keys[j] {
-                let r = a.keys[i]/a.ncol;
-                let c = a.keys[i] % a.ncol;
+    for i in 0..b.keys.len() {
+        let key = b.keys[i];
+        let r = key/b.ncol;
+        let c = key % b.ncol;
+        let nkey = (r+r_start)*a.ncol+(c+c_start);
+        b_keys.push(nkey);
+        b_data.push(b.data[i]);
+    }
 
-                if r < r_start || r > r_end || c < c_start || c > c_end {
-                    keys.push(a.keys[i]);
-                    data.push(a.data[i]);
-                } 
-                
-                i += 1;
-            }
-            else if a.keys[i] > b_keys[j] {
-                keys.push(b_keys[j]);
-                data.push(b_data[j]);
-                j += 1;
-            }
-            else {
-                keys.push(b_keys[j]);
-                data.push(b_data[j]);
-                i += 1;
-                j += 1;
-            }
-        }
+    let mut i:usize = 0;
+    let mut j:usize = 0;
 
-        while i < a.keys.len() {
+    while i < a.keys.len() && j < b_keys.len() {
+        if a.keys[i] < b_keys[j] {
             let r = a.keys[i]/a.ncol;
             let c = a.keys[i] % a.ncol;
 
             if r < r_start || r > r_end || c < c_start || c > c_end {
                 keys.push(a.keys[i]);
                 data.push(a.data[i]);
-            }
+            } 
+            
             i += 1;
         }
-
-        while j < b_keys.len() {
+        else if a.keys[i] > b_keys[j] {
+            keys.push(b_keys[j]);
+            data.push(b_data[j]);
+            j += 1;
+        }
+        else {
             keys.push(b_keys[j]);
             data.push(b_data[j]);
+            i += 1;
             j += 1;
         }
     }
+
+    while i < a.keys.len() {
+        let r = a.keys[i]/a.ncol;
+        let c = a.keys[i] % a.ncol;
+
+        if r < r_start || r > r_end || c < c_start || c > c_end {
+            keys.push(a.keys[i]);
+            data.push(a.data[i]);
+        }
+        i += 1;
+    }
+
+    while j < b_keys.len() {
+        keys.push(b_keys[j]);
+        data.push(b_data[j]);
+        j += 1;
+    }
     
-    return SparseMatrix::new(n, m, keys, data);
+    return Ok(SparseMatrix::new(n, m, keys, data));
 }
 
-pub fn vstack(a:&SparseMatrix, b:&SparseMatrix) -> SparseMatrix {
+#[allow(clippy::manual_memcpy)]
+pub fn vstack(a:&SparseMatrix, b:&SparseMatrix) -> Result<SparseMatrix, LinalgError> {
     let n1 = a.keys.len();
     let n2 = b.keys.len();
 
+    if a.ncol != b.ncol {
+        return Err(LinalgError::DimensionMismatch { expected: (b.nrow, a.ncol), found: (b.nrow, b.ncol) });
+    }
+
     let mut keys:Vec<usize> = vec![0;n1+n2];
     let mut data:Vec<f64> = vec![0.0;n1+n2];
 
-    let mut n = 0;
-    let mut m = 0;
+    let n = a.nrow + b.nrow;
+    let m = a.ncol;
 
-    if a.ncol == b.ncol {
-        n = a.nrow + b.nrow;
-        m = a.ncol;
-
-        const LANES:usize = 64;
+    const LANES:usize = 64;
 
-        for i in (0..n1).step_by(LANES) {
-            if i+LANES > n1 {
-                for j in i..n1 {
-                    keys[j] = a.keys[j];
-                    data[j] = a.data[j];
-                }
+    for i in (0..n1).step_by(LANES) {
+        if i+LANES > n1 {
+            for j in i..n1 {
+                keys[j] = a.keys[j];
+                data[j] = a.data[j];
             }
-            else {
-                let x:Simd<usize, LANES> = Simd::from_slice(&a.keys[i..i+LANES]);
-                Simd::copy_to_slice(x, &mut keys[i..i+LANES]);
+        }
+        else {
+            let x:Simd<usize, LANES> = Simd::from_slice(&a.keys[i..i+LANES]);
+            Simd::copy_to_slice(x, &mut keys[i..i+LANES]);
 
-                let x:Simd<f64, LANES> = Simd::from_slice(&a.data[i..i+LANES]);
-                Simd::copy_to_slice(x, &mut data[i..i+LANES]);
-            }
+            let x:Simd<f64, LANES> = Simd::from_slice(&a.data[i..i+LANES]);
+            Simd::copy_to_slice(x, &mut data[i..i+LANES]);
         }
+    }
 
-        for i in (0..n2).step_by(LANES) {
-            if i+LANES > n2 {
-                for j in i..n2 {
-                    keys[j+n1] = b.keys[j]+a.nrow*a.ncol;
-                    data[j+n1] = b.data[j];
-                }
+    for i in (0..n2).step_by(LANES) {
+        if i+LANES > n2 {
+            for j in i..n2 {
+                keys[j+n1] = b.keys[j]+a.nrow*a.ncol;
+                data[j+n1] = b.data[j];
             }
-            else {
-                let h:Simd<usize, LANES> = Simd::splat(a.nrow*a.ncol);
-                let x:Simd<usize, LANES> = Simd::from_slice(&b.keys[i..i+LANES]);
-                let y = x + h;
-                
-                Simd::copy_to_slice(y, &mut keys[i+n1..i+n1+LANES]);
+        }
+        else {
+            let h:Simd<usize, LANES> = Simd::splat(a.nrow*a.ncol);
+            let x:Simd<usize, LANES> = Simd::from_slice(&b.keys[i..i+LANES]);
+            let y = x + h;
+            
+            Simd::copy_to_slice(y, &mut keys[i+n1..i+n1+LANES]);
 
-                let x:Simd<f64, LANES> = Simd::from_slice(&b.data[i..i+LANES]);
-                Simd::copy_to_slice(x, &mut data[i+n1..i+n1+LANES]);
-            }
+            let x:Simd<f64, LANES> = Simd::from_slice(&b.data[i..i+LANES]);
+            Simd::copy_to_slice(x, &mut data[i+n1..i+n1+LANES]);
         }
     }
 
-    return SparseMatrix::new(n, m, keys, data);
+    return Ok(SparseMatrix::new(n, m, keys, data));
 }
 
 pub fn identity(n:usize) -> SparseMatrix {
@@ -507,82 +530,81 @@ pub fn norm(a:&SparseMatrix) -> f64 {
     return s.sqrt();
 }
 
-pub fn dot(a:&SparseMatrix, b:&SparseMatrix) -> SparseMatrix {
+pub fn dot(a:&SparseMatrix, b:&SparseMatrix) -> Result<SparseMatrix, LinalgError> {
     let mut keys:Vec<usize> = Vec::new();
     let mut data:Vec<f64> = Vec::new();
 
+    if a.ncol != b.nrow {
+        return Err(LinalgError::DimensionMismatch { expected: (a.ncol, b.ncol), found: (b.nrow, b.ncol) });
+    }
+
     let a_arc = Arc::new(a.clone());
     let b_arc = Arc::new(b.clone());
 
-    let mut n = 0;
-    let mut m = 0;
-
-    if a.ncol == b.nrow {
-        n = a.nrow;
-        m = b.ncol;
-
-        let n1 = a.keys.len();
-        let hmap = Arc::new(Mutex::new(vec![0.0;n*m]));
+    let n = a.nrow;
+    let m = b.ncol;
 
-        let mut handles = vec![];
-        let q = (n1 as f64/4.0).ceil() as usize;
-
-        for r in (0..n1).step_by(q) {
-            let a_curr = Arc::clone(&a_arc);
-            let b_curr = Arc::clone(&b_arc);
-            let h_map = Arc::clone(&hmap);
-            
-            let handle = thread::spawn(move || {
-                let mut i = r;
-                let mut h = h_map.lock().unwrap();
-
-                while i < min(r+q, n1)  {
-                    let a_key = a_curr.keys[i];
-                    let a_d = a_curr.data[i];
-                    let a_row = a_key/a_curr.ncol;
-                    let a_col = a_key % a_curr.ncol;
-
-                    let mut j = binary_search_next(&b_curr.keys, a_col*b_curr.ncol);
-                    
-                    while j < b_curr.keys.len() {
-                        let b_key = b_curr.keys[j];
-                        let b_d = b_curr.data[j];
-                        let b_row = b_key/b_curr.ncol;
-                        let b_col = b_key % b_curr.ncol;
-
-                        if b_row == a_col {
-                            let nkey = a_row*m+b_col;
-                            h[nkey] += a_d*b_d;
-                        }
-                        else {
-                            break;
-                        }
-
-                        j += 1;
+    let n1 = a.keys.len();
+    let hmap = Arc::new(Mutex::new(vec![0.0;n*m]));
+
+    let mut handles = vec![];
+    let q = ((n1 as f64/4.0).ceil() as usize).max(1);
+
+    for r in (0..n1).step_by(q) {
+        let a_curr = Arc::clone(&a_arc);
+        let b_curr = Arc::clone(&b_arc);
+        let h_map = Arc::clone(&hmap);
+        
+        let handle = thread::spawn(move || {
+            let mut i = r;
+            let mut h = h_map.lock().unwrap();
+
+            while i < min(r+q, n1)  {
+                let a_key = a_curr.keys[i];
+                let a_d = a_curr.data[i];
+                let a_row = a_key/a_curr.ncol;
+                let a_col = a_key % a_curr.ncol;
+
+                let mut j = binary_search_next(&b_curr.keys, a_col*b_curr.ncol);
+                
+                while j < b_curr.keys.len() {
+                    let b_key = b_curr.keys[j];
+                    let b_d = b_curr.data[j];
+                    let b_row = b_key/b_curr.ncol;
+                    let b_col = b_key % b_curr.ncol;
+
+                    if b_row == a_col {
+                        let nkey = a_row*m+b_col;
+                        h[nkey] += a_d*b_d;
+                    }
+                    else {
+                        break;
                     }
 
-                    i += 1;
+                    j += 1;
                 }
-            });
 
-            handles.push(handle);
-        }
+                i += 1;
+            }
+        });
 
-        for handle in handles {
-            handle.join().unwrap();
-        }
+        handles.push(handle);
+    }
 
-        let h = hmap.lock().unwrap();
+    for handle in handles {
+        handle.join().unwrap();
+    }
 
-        for i in 0..h.len() {
-            if h[i].abs() > 1e-10 {
-                keys.push(i);
-                data.push(h[i]);
-            }
+    let h = hmap.lock().unwrap();
+
+    for i in 0..h.len() {
+        if h[i].abs() > 1e-10 {
+            keys.push(i);
+            data.push(h[i]);
         }
     }
     
-    return SparseMatrix::new(n, m, keys, data);
+    return Ok(SparseMatrix::new(n, m, keys, data));
 }
 
 pub fn convert_to_array(a:&SparseMatrix) -> Vec<f64> {
@@ -596,73 +618,3 @@ pub fn convert_to_array(a:&SparseMatrix) -> Vec<f64> {
 
     return out;
 }
-
-pub fn run() {
-    let n = 500;
-    let m = 500;
-    let k = 0; //4*n*m/5;
-
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    
-    let mut a:Vec<f64> = vec![0.0;n*m];
-    let mut b:Vec<f64> = vec![0.0;n*m];
-
-    for i in 0..n*m {
-        a[i] = normal.sample(&mut rng);
-        b[i] = normal.sample(&mut rng);
-    }
-
-    let uniform = Uniform::new(0, n*m);
-
-    for _ in 0..k {
-        let j = uniform.sample(&mut rng);
-        a[j] = 0.0;
-    }
-
-    for _ in 0..k {
-        let j = uniform.sample(&mut rng);
-        b[j] = 0.0;
-    }
-
-    let c = SparseMatrix::create(n, m, &a);
-    let d = SparseMatrix::create(m, n, &b);
-
-    // let e = get_sub_mat(&c, 1, 3, 2, 4);
-    // let f = convert_to_array(&e);
-
-    // let g = add(&c, &d);
-    // let h = convert_to_array(&g);
-
-    // let j = mul(&c, &d);
-    // let k = convert_to_array(&j);
-
-    // let c1 = Arc::new(c);
-    // let d1 = Arc::new(d);
-
-    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
-    let l = dot(&c, &d);
-    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
-    println!("{:?}", end_time-start_time);
-    // let m = convert_to_array(&l);
-
-    // let q = transpose(&c);
-    // let y = convert_to_array(&q);
-
-    // println!("{:?}", a);
-    // println!();
-    // println!("{:?}", b);
-    // println!();
-    // println!("{:?}", f);
-    // println!();
-    // println!("{:?}", h);
-    // println!();
-    // println!("{:?}", k);
-    // println!();
-    // println!("{:?}", m);
-    // println!();
-    // println!("{:?}", y);
-}
-
-
-
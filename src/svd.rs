@@ -1,20 +1,36 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::needless_return)]
 use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
 use crate::orthogonal_matrices::*;
 use crate::qr_decomposition::*;
+use crate::convergence::{IterOptions, Convergence};
+use crate::jacobi::jacobi_svd;
 use rand_distr::{Distribution, Normal};
-use rand::thread_rng;
+use rand::{SeedableRng, rngs::StdRng};
+use crate::linear_operator::LinearOperator;
 use std::cmp::min;
-use std::time::SystemTime;
 
-pub fn golub_kahan(a:&mut [f64], l:&mut [f64], r:&mut [f64], n:usize, m:usize, z:usize, i:usize, j:usize) {
-    let mu = eigenvalue_bidiagonal_slices(&a, z, z, i, j, i, j);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SvdBackend {
+    // Householder bidiagonalization and implicit QR sweeps.
+    #[default]
+    GolubKahan,
+    // One-sided Jacobi: slower, but small singular values keep their relative accuracy.
+    Jacobi,
+}
+
+// One implicit QR sweep on the unreduced block i..=j of the bidiagonal a. The rotations are
+// accumulated into the rows of l and the columns of r when they are given.
+pub fn golub_kahan(a:&mut Matrix, mut l:Option<&mut Matrix>, mut r:Option<&mut Matrix>, i:usize, j:usize) {
+    let z = a.nrow;
+    let mu = eigenvalue_bidiagonal_slices(a.as_slice(), z, z, i, j, i, j);
     
-    let u = a[i*z+i];
-    let v = a[i*z+i+1];
+    let u = a[(i, i)];
+    let v = a[(i, i+1)];
     
-    a[i*z+i] = u*u-mu;
-    a[i*z+i+1] = u*v;
+    a[(i, i)] = u*u-mu;
+    a[(i, i+1)] = u*v;
     
     for k in i..j {
         let mut x;
@@ -29,15 +45,17 @@ pub fn golub_kahan(a:&mut [f64], l:&mut [f64], r:&mut [f64], n:usize, m:usize, z
             y = i+1; 
         }
 
-        let b = givens_right_rotation(&a, z, z, x, y, false);
+        let b = givens_right_rotation(a.view(), x, y, false);
 
         if k == i {
-            a[i*z+i] = u;
-            a[i*z+i+1] = v;
+            a[(i, i)] = u;
+            a[(i, i+1)] = v;
         }
 
-        givens_right_rotation_multiply(a, z, z, b.0, b.1, x, y, i, j, i, j);
-        givens_right_rotation_multiply(r, m, z, b.0, b.1, x, y, 0, m-1, 0, z-1);
+        givens_right_rotation_multiply(a.view_mut().subview_mut(i..j+1, 0..z), b.0, b.1, y);
+        if let Some(r) = r.as_deref_mut() {
+            givens_right_rotation_multiply(r.view_mut(), b.0, b.1, y);
+        }
 
         if k > i {
             x = k+1;
@@ -48,44 +66,101 @@ pub fn golub_kahan(a:&mut [f64], l:&mut [f64], r:&mut [f64], n:usize, m:usize, z
             y = i;
         }
             
-        let b = givens_left_rotation(&a, z, z, x, y, false);
+        let b = givens_left_rotation(a.view(), x, y, false);
 
-        givens_left_rotation_multiply(a, z, z, b.0, b.1, x, y, i, j, i, j);
-        givens_left_rotation_multiply(l, z, n, b.0, b.1, x, y, 0, z-1, 0, n-1);
+        givens_left_rotation_multiply(a.view_mut().subview_mut(0..z, i..j+1), b.0, b.1, x);
+        if let Some(l) = l.as_deref_mut() {
+            givens_left_rotation_multiply(l.view_mut(), b.0, b.1, x);
+        }
     }
 }
 
-pub fn golub_reisch_svd(a:&[f64], mut n:usize, mut m:usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
-    let mut a1 = a.to_vec();
-    let mut do_transpose = false;
+pub fn golub_reisch_svd(a:&Matrix) -> Result<(Matrix, Matrix, Matrix), LinalgError> {
+    let (u, s, vt, conv) = golub_reisch_svd_with_options(a, &IterOptions::default())?;
+    conv.check()?;
+    return Ok((u, s, vt));
+}
 
-    if n < m {
-        do_transpose = true;
-        a1 = transpose(&a1, n, m);
-        let g = n;
-        n = m;
-        m = g;
+// x = cx + sy, y = cy - sx on rows i and j of a (column is false) or on its columns.
+fn rotate_lines(a:&mut Matrix, i:usize, j:usize, c:f64, s:f64, column:bool) {
+    let len = if column { a.nrow } else { a.ncol };
+    for k in 0..len {
+        let (pi, pj) = if column { ((k, i), (k, j)) } else { ((i, k), (j, k)) };
+        let x = a[pi];
+        let y = a[pj];
+        a[pi] = c*x+s*y;
+        a[pj] = c*y-s*x;
     }
-    
-    let hr = householder_reflection_bidiagonalization(&a1, n, m);
-    let r = min(n, m);
+}
 
-    let mut u = sub_mat(&hr.0, n, n, 0, r-1, 0, n-1);
-    a1 = sub_mat(&hr.1, n, m, 0, r-1, 0, r-1);
-    let mut v = sub_mat(&hr.2, m, m, 0, m-1, 0, r-1);
-    
-    let eps = 1e-7;
+// With a zero diagonal at i in the unreduced block p..=q, rotations decouple the block there:
+// row rotations against the rows below push the superdiagonal of row i off the right end, or
+// for i = q, column rotations against the columns to the left push the superdiagonal above it
+// off the top. Either way a superdiagonal becomes zero and the block splits.
+fn chase_zero_diagonal(a1:&mut Matrix, mut u:Option<&mut Matrix>, mut v:Option<&mut Matrix>, p:usize, q:usize, i:usize) {
+    if i < q {
+        for j in i+1..=q {
+            let x = a1[(j, j)];
+            let y = a1[(i, j)];
+            let r = x.hypot(y);
+            if r == 0.0 {
+                continue;
+            }
+            rotate_lines(a1, j, i, x/r, y/r, false);
+            a1[(i, j)] = 0.0;
+            if let Some(u) = u.as_deref_mut() {
+                rotate_lines(u, j, i, x/r, y/r, false);
+            }
+        }
+    }
+    else {
+        for j in (p..q).rev() {
+            let x = a1[(j, j)];
+            let y = a1[(j, q)];
+            let r = x.hypot(y);
+            if r == 0.0 {
+                continue;
+            }
+            rotate_lines(a1, j, q, x/r, y/r, true);
+            a1[(j, q)] = 0.0;
+            if let Some(v) = v.as_deref_mut() {
+                rotate_lines(v, j, q, x/r, y/r, true);
+            }
+        }
+    }
+}
+
+// Implicit QR sweeps on the r x r upper bidiagonal a1 until it is diagonal, rotating the rows
+// of u and the columns of v when they are given. Superdiagonals at or below
+// tol*(|b_ii| + |b_i+1,i+1|) and diagonals at or below tol*|B| are dropped; max_iter defaults
+// to 6r^2 sweeps.
+fn bidiagonal_svd(a1:&mut Matrix, mut u:Option<&mut Matrix>, mut v:Option<&mut Matrix>, options:&IterOptions) -> Convergence {
+    let r = a1.nrow;
+    let eps = options.tol_or(f64::EPSILON);
+    let max_iter = options.max_iter_or(6*r*r);
+    let mut iter = 0;
+    let mut residual:f64 = 0.0;
+    let mut converged = true;
+
+    let bnorm = a1.as_slice().iter().fold(0.0, |acc:f64, x| acc.max(x.abs()));
+    for i in 0..r {
+        if a1[(i, i)] != 0.0 && a1[(i, i)].abs() <= eps*bnorm {
+            residual = residual.max(a1[(i, i)].abs());
+            a1[(i, i)] = 0.0;
+        }
+    }
     
     loop {
         for i in 0..r-1 {
-            if a1[i*r+i+1].abs() < eps*(a1[i*r+i].abs() + a1[(i+1)*r+i+1].abs()) {
-                a1[i*r+i+1] = 0.0;
+            if a1[(i, i+1)] != 0.0 && a1[(i, i+1)].abs() <= eps*(a1[(i, i)].abs() + a1[(i+1, i+1)].abs()) {
+                residual = residual.max(a1[(i, i+1)].abs());
+                a1[(i, i+1)] = 0.0;
             }
         }
 
         let mut q = 0;
         for i in (0..r-1).rev() {
-            if a1[i*r+i+1].abs() > 0.0 {
+            if a1[(i, i+1)].abs() > 0.0 {
                 q = i+1;
                 break;
             }
@@ -95,9 +170,16 @@ pub fn golub_reisch_svd(a:&[f64], mut n:usize, mut m:usize) -> (Vec<f64>, Vec<f6
             break;
         }
 
+        if iter == max_iter {
+            residual = (0..q).fold(residual, |acc, i| acc.max(a1[(i, i+1)].abs()));
+            converged = false;
+            break;
+        }
+        iter += 1;
+
         let mut p = 0;
         for i in (0..q).rev() {
-            if a1[i*r+i+1].abs() == 0.0 {
+            if a1[(i, i+1)].abs() == 0.0 {
                 p = i+1;
                 break;
             }
@@ -105,83 +187,448 @@ pub fn golub_reisch_svd(a:&[f64], mut n:usize, mut m:usize) -> (Vec<f64>, Vec<f6
 
         let mut flag: bool = false;
         
-        for i in p..q {
-            if a1[i*r+i].abs() == 0.0 {
+        for i in p..=q {
+            if a1[(i, i)].abs() == 0.0 {
                 flag = true;
-                for j in i+1..r {
-                    let b = givens_left_rotation(&a1, r, r, i+1, j, true);
-                    
-                    givens_left_rotation_multiply(&mut a1, r, r, b.0, b.1, i+1, j, 0, r-1, 0, r-1);
-                    givens_left_rotation_multiply(&mut u, r, n, b.0, b.1, i+1, j, 0, r-1, 0, n-1);
-                }
+                chase_zero_diagonal(a1, u.as_deref_mut(), v.as_deref_mut(), p, q, i);
+                break;
             }
         }
 
         if !flag && p < q {
-            golub_kahan(&mut a1, &mut u, &mut v, n, m, r, p, q);
+            golub_kahan(a1, u.as_deref_mut(), v.as_deref_mut(), p, q);
         }
     }
 
+    return Convergence { iterations: iter, residual, converged };
+}
+
+// As golub_reisch_svd, reporting convergence instead of failing when max_iter is reached.
+// The singular values are left on the diagonal of S as they converge, in no particular order
+// and possibly negative; svd returns them sorted.
+pub fn golub_reisch_svd_with_options(a:&Matrix, options:&IterOptions) -> Result<(Matrix, Matrix, Matrix, Convergence), LinalgError> {
+    check_finite(a.as_slice())?;
+
+    let (mut n, mut m) = a.shape();
+    if min(n, m) == 0 {
+        let conv = Convergence { iterations: 0, residual: 0.0, converged: true };
+        return Ok((Matrix::zeros(n, 0), Matrix::zeros(0, 0), Matrix::zeros(0, m), conv));
+    }
+
+    let mut a1 = a.clone();
+    let mut do_transpose = false;
+
+    if n < m {
+        do_transpose = true;
+        a1 = a1.transpose();
+        std::mem::swap(&mut n, &mut m);
+    }
+
+    let bd = HouseholderBidiagonal::new(&a1);
+    let r = min(n, m);
+
+    let mut u = bd.q_left(r).transpose();
+    a1 = bd.b();
+    let mut v = bd.q_right();
+
+    let conv = bidiagonal_svd(&mut a1, Some(&mut u), Some(&mut v), options);
+
     if do_transpose {
-        return (v, transpose(&a1, r, r), u);
+        return Ok((v, a1.transpose(), u, conv));
     }
         
-    return (transpose(&u, r, n), a1, transpose(&v, m, r));
+    return Ok((u.transpose(), a1, v.transpose(), conv));
 }
 
-pub fn randomized_svd(a:&[f64], n:usize, m:usize, k:usize) -> (Vec<f64>, Vec<f64>, Vec<f64>){
-    let l = min(k+10, m);
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    
-    let mut p:Vec<f64> = vec![0.0;m*l];
+// A = U diag(s) V' with s non-negative and non-increasing, U and V' as selected by SvdMode.
+#[derive(Clone, Debug)]
+pub struct Svd {
+    pub u: Option<Matrix>,
+    pub s: Vec<f64>,
+    pub vt: Option<Matrix>,
+    pub convergence: Convergence,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SvdMode {
+    // U is n x n and V' is m x m, completed to orthonormal bases.
+    Full,
+    // U is n x r and V' is r x m, r = min(n, m).
+    #[default]
+    Thin,
+    // Singular values only; the rotations of the QR sweeps are not accumulated.
+    ValuesOnly,
+}
+
+pub fn svd(a:&Matrix, mode:SvdMode) -> Result<Svd, LinalgError> {
+    let out = svd_with_options(a, mode, &IterOptions::default())?;
+    out.convergence.check()?;
+    return Ok(out);
+}
+
+// As svd, reporting convergence instead of failing when max_iter is reached. Singular values
+// that came out of the sweeps negative are made positive by flipping one of their vectors.
+pub fn svd_with_options(a:&Matrix, mode:SvdMode, options:&IterOptions) -> Result<Svd, LinalgError> {
+    check_finite(a.as_slice())?;
 
-    for i in 0..m*l {
-        p[i] = normal.sample(&mut rng);
+    let (n, m) = a.shape();
+    let r = min(n, m);
+    let want = mode != SvdMode::ValuesOnly;
+    let full = mode == SvdMode::Full;
+
+    if r == 0 {
+        let (u, vt) = if full { (Matrix::identity(n), Matrix::identity(m)) } else { (Matrix::zeros(n, 0), Matrix::zeros(0, m)) };
+        let convergence = Convergence { iterations: 0, residual: 0.0, converged: true };
+        return Ok(Svd { u: want.then_some(u), s: vec![], vt: want.then_some(vt), convergence });
     }
 
-    let b = matrix_multiply_simd(&a, &p, n, m, l);
-    let (mut q, _r, n1, m1, _p1) = givens_rotation_qr(&b, n, l);
-    let w= min(m1, l);
-    q = sub_mat(&q, n1, m1, 0, n1-1, 0, w-1);
+    // Work on the tall one of A and A', n1 >= r.
+    let a1 = if n < m { a.transpose() } else { a.clone() };
+    let n1 = a1.nrow;
+
+    // Q_L is formed only as far as the mode needs: all n1 columns for Full, r for Thin and
+    // none for ValuesOnly, where the sweeps rotate B alone.
+    let bd = HouseholderBidiagonal::new(&a1);
+    let mut b = bd.b();
+
+    if !want {
+        let convergence = bidiagonal_svd(&mut b, None, None, options);
+        let mut s:Vec<f64> = b.diag().iter().map(|x| x.abs()).collect();
+        s.sort_by(|p, q| q.total_cmp(p));
+        return Ok(Svd { u: None, s, vt: None, convergence });
+    }
+
+    let mut ut = bd.q_left(if full { n1 } else { r }).transpose();
+    let mut v = bd.q_right();
+    let convergence = bidiagonal_svd(&mut b, Some(&mut ut), Some(&mut v), options);
+
+    let d = b.diag();
+    let mut order:Vec<usize> = (0..r).collect();
+    order.sort_by(|&p, &q| d[q].abs().total_cmp(&d[p].abs()));
+    let s:Vec<f64> = order.iter().map(|&i| d[i].abs()).collect();
+
+    // Left vectors are the rows of ut, right vectors the columns of v; rows of ut past r
+    // complete the basis in full mode and keep their place.
+    let k = ut.nrow;
+    let mut u1 = Matrix::zeros(n1, k);
+    let mut vt1 = Matrix::zeros(r, r);
+    for (j, &i) in order.iter().enumerate() {
+        let sign = if d[i] < 0.0 { -1.0 } else { 1.0 };
+        for l in 0..n1 {
+            u1[(l, j)] = ut[(i, l)];
+        }
+        for l in 0..r {
+            vt1[(j, l)] = sign*v[(l, i)];
+        }
+    }
+    for j in r..k {
+        for l in 0..n1 {
+            u1[(l, j)] = ut[(j, l)];
+        }
+    }
+
+    if n < m {
+        return Ok(Svd { u: Some(vt1.transpose()), s, vt: Some(u1.transpose()), convergence });
+    }
+    return Ok(Svd { u: Some(u1), s, vt: Some(vt1), convergence });
+}
 
-    let c = &matrix_multiply_simd(&transpose(&q, n1, w), &a, w, n1, m);
-    let (mut u, s, v) = golub_reisch_svd(&c, w, m);
-    u = matrix_multiply_simd(&q, &u, n1, w, w);
-    return (u, s, v);
+// Thin SVD of A with the algorithm chosen by backend. Both return the same Svd, with the
+// singular values non-negative and in non-increasing order.
+pub fn svd_with_backend(a:&Matrix, backend:SvdBackend, options:&IterOptions) -> Result<Svd, LinalgError> {
+    return match backend {
+        SvdBackend::GolubKahan => svd_with_options(a, SvdMode::Thin, options),
+        SvdBackend::Jacobi => {
+            let (u, s, vt, convergence) = jacobi_svd(a, options)?;
+            Ok(Svd { u: Some(u), s: s.diag(), vt: Some(vt), convergence })
+        }
+    };
 }
 
-pub fn run() {
-    let n = 500;
-    let m = 500;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RandomizedSvdOptions {
+    // Sample columns drawn beyond k; more make the captured range closer to the top-k one.
+    pub oversampling: usize,
+    // Passes through A'A, each re-orthonormalized, which sharpen a slowly decaying spectrum.
+    pub power_iterations: usize,
+    // Seed of the Gaussian test matrix; None draws a fresh one from the OS on every call.
+    pub seed: Option<u64>,
+}
 
-    let mut rng = thread_rng();
-    let normal:Normal<f64> = Normal::new(0.0, 1.0).ok().unwrap();
-    
-    let mut a:Vec<f64> = vec![0.0;n*m];
+impl Default for RandomizedSvdOptions {
+    fn default() -> Self {
+        Self {
+            oversampling: 10,
+            power_iterations: 2,
+            seed: None,
+        }
+    }
+}
 
-    for i in 0..n*m {
-        a[i] = normal.sample(&mut rng);
+impl RandomizedSvdOptions {
+    pub fn new(oversampling:usize, power_iterations:usize, seed:Option<u64>) -> Self {
+        Self {
+            oversampling,
+            power_iterations,
+            seed,
+        }
     }
+}
 
-    let start_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
-    let b = golub_reisch_svd(&a, n, m);
-    let end_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
-    println!("{:?}", end_time-start_time);
+// Rank-k approximation A ~ U diag(s) V' by randomized range finding (Halko, Martinsson and
+// Tropp): Q spans A times a Gaussian m x (k + oversampling) block, refined by the power
+// iterations, and the SVD of the small Q'A gives the factors. Only products with A and A' are
+// used, so sparse input works as is. U is n x k and V' is k x m.
+pub fn randomized_svd<A:LinearOperator+?Sized>(a:&A, k:usize, options:&RandomizedSvdOptions) -> Result<Svd, LinalgError> {
+    let (n, m) = a.shape();
     let r = min(n, m);
-    let mut c = matrix_multiply_simd(&b.0, &b.1, n, r, r);
-    c = matrix_multiply_simd(&c, &b.2, n, r, m);
+    if k == 0 || k > r {
+        return Err(LinalgError::InvalidRank { rank: k, max: r });
+    }
 
-    // println!("{:?}", a);
-    // println!();
-    // println!("{:?}", b.1);
-    // println!();
-    // println!("{:?}", c);
+    let l = min(k+options.oversampling, r);
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let normal:Normal<f64> = Normal::new(0.0, 1.0).unwrap();
 
-    for i in 0..n*m {
-        assert!((c[i]-a[i]).abs() < 1e-5, "Some issue in SVD !!! {}, {}", a[i], c[i]);
+    let mut omega = Matrix::zeros(m, l);
+    for i in 0..m {
+        for j in 0..l {
+            omega[(i, j)] = normal.sample(&mut rng);
+        }
     }
+
+    let y = a.apply_matrix(&omega);
+    check_finite(y.as_slice())?;
+    let (mut q, _) = thin_qr(&y);
+
+    for _ in 0..options.power_iterations {
+        let (z, _) = thin_qr(&a.apply_transpose_matrix(&q));
+        (q, _) = thin_qr(&a.apply_matrix(&z));
+    }
+
+    // B = Q'A, formed as (A'Q)'.
+    let b = a.apply_transpose_matrix(&q).transpose();
+    let small = svd(&b, SvdMode::Thin)?;
+    let ub = small.u.unwrap().view().subview(0..l, 0..k).to_matrix();
+
+    return Ok(Svd {
+        u: Some(&q*&ub),
+        s: small.s[0..k].to_vec(),
+        vt: Some(small.vt.unwrap().view().subview(0..k, 0..m).to_matrix()),
+        convergence: small.convergence,
+    });
 }
 
+// Singular values above rcond*s_max count as nonzero; rcond defaults to max(n, m)*eps, as in
+// lstsq. s must be non-increasing.
+pub(crate) fn numerical_rank(s:&[f64], n:usize, m:usize, rcond:Option<f64>) -> usize {
+    if s.is_empty() {
+        return 0;
+    }
+    let rcond = rcond.unwrap_or((n.max(m) as f64)*f64::EPSILON);
+    let cutoff = rcond*s[0];
+    return s.iter().filter(|&&x| x > cutoff).count();
+}
 
+pub fn matrix_rank(a:&Matrix, rcond:Option<f64>) -> Result<usize, LinalgError> {
+    let f = svd(a, SvdMode::ValuesOnly)?;
+    return Ok(numerical_rank(&f.s, a.nrow, a.ncol, rcond));
+}
 
+// Orthonormal basis of the null space {x : Ax = 0} as the columns of an m x (m - rank) matrix.
+pub fn null_space(a:&Matrix, rcond:Option<f64>) -> Result<Matrix, LinalgError> {
+    let m = a.ncol;
+    // Thin V' is already m x m unless A is wide.
+    let mode = if a.nrow >= m { SvdMode::Thin } else { SvdMode::Full };
+    let f = svd(a, mode)?;
+    let rank = numerical_rank(&f.s, a.nrow, m, rcond);
+    return Ok(f.vt.unwrap().view().subview(rank..m, 0..m).t().to_matrix());
+}
+
+// Orthonormal basis of the range of A as the columns of an n x rank matrix.
+pub fn orth(a:&Matrix, rcond:Option<f64>) -> Result<Matrix, LinalgError> {
+    let n = a.nrow;
+    let f = svd(a, SvdMode::Thin)?;
+    let rank = numerical_rank(&f.s, n, a.ncol, rcond);
+    return Ok(f.u.unwrap().view().subview(0..n, 0..rank).to_matrix());
+}
+
+// Largest singular value, the operator 2-norm.
+pub fn spectral_norm(a:&Matrix) -> Result<f64, LinalgError> {
+    let f = svd(a, SvdMode::ValuesOnly)?;
+    return Ok(f.s.first().copied().unwrap_or(0.0));
+}
+
+// Sum of the singular values.
+pub fn nuclear_norm(a:&Matrix) -> Result<f64, LinalgError> {
+    let f = svd(a, SvdMode::ValuesOnly)?;
+    return Ok(f.s.iter().sum());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric_eigen::symmetric_eigenvalues;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random(n:usize, m:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Matrix::new(n, m, (0..n*m).map(|_| rng.gen_range(-1.0..1.0)).collect());
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    // Upper bidiagonal input with an exact zero on the diagonal at every position, checked
+    // against the eigenvalues of B'B.
+    #[test]
+    fn svd_zero_diagonal() {
+        for z in 0..4 {
+            let mut b = Matrix::zeros(4, 4);
+            for i in 0..4 {
+                b[(i, i)] = (i+1) as f64;
+                if i < 3 {
+                    b[(i, i+1)] = 0.5;
+                }
+            }
+            b[(z, z)] = 0.0;
+
+            let f = svd(&b, SvdMode::Thin).unwrap();
+            let mut w = symmetric_eigenvalues(&b.tr_mul(&b)).unwrap();
+            w.reverse();
+            assert!(f.convergence.converged);
+            for i in 0..4 {
+                assert!((f.s[i]*f.s[i]-w[i]).abs() <= 1e-12, "zero at {}: {:?} vs {:?}", z, f.s, w);
+            }
+
+            let (u, vt) = (f.u.unwrap(), f.vt.unwrap());
+            let us = Matrix::new(4, 4, (0..16).map(|k| u[(k/4, k%4)]*f.s[k%4]).collect());
+            assert!((&(&us*&vt)-&b).norm() <= 1e-12);
+        }
+    }
+
+    #[test]
+    fn modes_agree_and_reconstruct() {
+        for &(n, m) in &[(9, 5), (5, 9), (6, 6), (1, 4)] {
+            let a = random(n, m, (n*m) as u64);
+            let r = n.min(m);
+
+            let values = svd(&a, SvdMode::ValuesOnly).unwrap();
+            assert!(values.u.is_none() && values.vt.is_none());
+            assert!(values.s.windows(2).all(|p| p[0] >= p[1]) && values.s[r-1] >= 0.0);
+
+            let thin = svd(&a, SvdMode::Thin).unwrap();
+            let (u, vt) = (thin.u.unwrap(), thin.vt.unwrap());
+            assert_eq!((u.shape(), vt.shape()), ((n, r), (r, m)));
+            assert!(max_abs_diff(&(&(&u * &Matrix::from_diag(&thin.s)) * &vt), &a) < 1e-12);
+            for i in 0..r {
+                assert!((thin.s[i]-values.s[i]).abs() < 1e-12);
+            }
+
+            let full = svd(&a, SvdMode::Full).unwrap();
+            let (uf, vtf) = (full.u.unwrap(), full.vt.unwrap());
+            assert_eq!((uf.shape(), vtf.shape()), ((n, n), (m, m)));
+            assert!(max_abs_diff(&uf.tr_mul(&uf), &Matrix::identity(n)) < 1e-12);
+            assert!(max_abs_diff(&vtf.mul_tr(&vtf), &Matrix::identity(m)) < 1e-12);
+            let mut s = Matrix::zeros(n, m);
+            for i in 0..r {
+                s[(i, i)] = full.s[i];
+            }
+            assert!(max_abs_diff(&(&(&uf * &s) * &vtf), &a) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn empty_input_has_no_singular_values() {
+        let f = svd(&Matrix::zeros(3, 0), SvdMode::Full).unwrap();
+        assert!(f.s.is_empty());
+        assert_eq!(f.u.unwrap().shape(), (3, 3));
+        assert_eq!(f.vt.unwrap().shape(), (0, 0));
+        assert!(svd(&Matrix::zeros(0, 2), SvdMode::ValuesOnly).unwrap().u.is_none());
+    }
+
+    // n x m matrix of rank k with singular values 1, 1/2, ..., 1/2^(k-1).
+    fn low_rank(n:usize, m:usize, k:usize, seed:u64) -> Matrix {
+        let (u, _) = thin_qr(&random(n, k, seed));
+        let (v, _) = thin_qr(&random(m, k, seed+1));
+        let d:Vec<f64> = (0..k).map(|i| 0.5f64.powi(i as i32)).collect();
+        return &(&u * &Matrix::from_diag(&d)) * &v.transpose();
+    }
+
+    #[test]
+    fn randomized_svd_recovers_low_rank_matrix() {
+        let a = low_rank(40, 30, 5, 7);
+        let options = RandomizedSvdOptions::new(5, 1, Some(1));
+        let f = randomized_svd(&a, 5, &options).unwrap();
+        for i in 0..5 {
+            assert!((f.s[i]-0.5f64.powi(i as i32)).abs() < 1e-12);
+        }
+        let (u, vt) = (f.u.unwrap(), f.vt.unwrap());
+        assert_eq!((u.shape(), vt.shape()), ((40, 5), (5, 30)));
+        assert!(max_abs_diff(&(&(&u * &Matrix::from_diag(&f.s)) * &vt), &a) < 1e-12);
+    }
+
+    #[test]
+    fn randomized_svd_is_reproducible_with_a_seed() {
+        let a = random(25, 20, 8);
+        let options = RandomizedSvdOptions::new(4, 2, Some(42));
+        let f = randomized_svd(&a, 3, &options).unwrap();
+        let g = randomized_svd(&a, 3, &options).unwrap();
+        assert_eq!(f.s, g.s);
+        assert_eq!(f.u.unwrap().as_slice(), g.u.unwrap().as_slice());
+
+        // With the full range sampled the leading values are exact.
+        let exact = svd(&a, SvdMode::ValuesOnly).unwrap().s;
+        let full = randomized_svd(&a, 3, &RandomizedSvdOptions::new(20, 0, Some(1))).unwrap();
+        for (x, y) in full.s.iter().zip(exact.iter()) {
+            assert!((x-y).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn randomized_svd_rejects_bad_rank() {
+        let a = random(4, 6, 9);
+        let options = RandomizedSvdOptions::default();
+        assert!(matches!(randomized_svd(&a, 0, &options), Err(LinalgError::InvalidRank { .. })));
+        assert!(matches!(randomized_svd(&a, 5, &options), Err(LinalgError::InvalidRank { .. })));
+    }
+
+
+    #[test]
+    fn rank_null_space_and_orth() {
+        // Rank 3 in both orientations, plus a full-rank tall case with a trivial null space.
+        for &(n, m) in &[(8, 6), (6, 8)] {
+            let a = low_rank(n, m, 3, 10);
+            assert_eq!(matrix_rank(&a, None).unwrap(), 3);
+
+            let z = null_space(&a, None).unwrap();
+            assert_eq!(z.shape(), (m, m-3));
+            assert!(max_abs_diff(&(&a * &z), &Matrix::zeros(n, m-3)) < 1e-12);
+            assert!(max_abs_diff(&z.tr_mul(&z), &Matrix::identity(m-3)) < 1e-12);
+
+            let q = orth(&a, None).unwrap();
+            assert_eq!(q.shape(), (n, 3));
+            assert!(max_abs_diff(&q.tr_mul(&q), &Matrix::identity(3)) < 1e-12);
+            // The range is spanned: projecting A onto it changes nothing.
+            assert!(max_abs_diff(&(&q * &q.tr_mul(&a)), &a) < 1e-12);
+        }
+
+        let a = random(7, 4, 11);
+        assert_eq!(matrix_rank(&a, None).unwrap(), 4);
+        assert_eq!(null_space(&a, None).unwrap().shape(), (4, 0));
+        assert_eq!(matrix_rank(&Matrix::zeros(3, 2), None).unwrap(), 0);
+    }
+
+    #[test]
+    fn spectral_and_nuclear_norms() {
+        let a = low_rank(9, 7, 4, 12);
+        assert!((spectral_norm(&a).unwrap()-1.0).abs() < 1e-12);
+        assert!((nuclear_norm(&a).unwrap()-1.875).abs() < 1e-12);
+        assert_eq!(spectral_norm(&Matrix::zeros(0, 3)).unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,409 @@
+#![allow(dead_code, clippy::needless_return)]
+use crate::matrix_utils::*;
+use crate::dense_matrix::Matrix;
+use crate::linalg_error::*;
+use crate::orthogonal_matrices::*;
+use crate::convergence::{IterOptions, Convergence};
+use crate::jacobi::jacobi_eigen;
+use crate::cholesky::cholesky;
+use crate::solve_linear::{forward_sub, backward_sub};
+
+// Sweeps allowed per eigenvalue before giving up; two or three are typical.
+const SWEEPS_PER_EIGENVALUE:usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum EigenBackend {
+    // Householder tridiagonalization and implicit QR with Wilkinson shifts.
+    #[default]
+    TridiagonalQr,
+    // Cyclic Jacobi rotations: slower, but small eigenvalues keep their relative accuracy.
+    Jacobi,
+}
+
+// Householder reduction A = QTQ' of symmetric A to tridiagonal T with diagonal d and
+// off-diagonal e. Only the lower triangle of A is read. Q is formed when want_q is set.
+pub fn tridiagonalize(a:&Matrix, want_q:bool) -> (Vec<f64>, Vec<f64>, Option<Matrix>) {
+    let n = a.nrow;
+    let mut w = a.clone();
+
+    for i in 0..n {
+        for j in i+1..n {
+            w[(i, j)] = w[(j, i)];
+        }
+    }
+
+    let mut tau = vec![0.0;n.saturating_sub(1)];
+
+    for i in 0..n.saturating_sub(2) {
+        let mut x = w.view().subview(i+1..n, i..i+1).to_vec();
+        tau[i] = householder_reflector(&mut x);
+        let beta = x[0];
+        x[0] = 1.0;
+
+        if tau[i] != 0.0 {
+            // A22 -= vw' + wv' with p = tau A22 v and w = p - (tau/2)(p'v)v.
+            let mut p = matrix_vector_multiply(w.view().subview(i+1..n, i+1..n), &x).unwrap();
+            for v in p.iter_mut() {
+                *v *= tau[i];
+            }
+            let alpha = -0.5*tau[i]*dot_product_simd(&p, &x);
+            for k in 0..p.len() {
+                p[k] += alpha*x[k];
+            }
+
+            rank_one_update(w.view_mut().subview_mut(i+1..n, i+1..n), -1.0, &x, &p).unwrap();
+            rank_one_update(w.view_mut().subview_mut(i+1..n, i+1..n), -1.0, &p, &x).unwrap();
+        }
+
+        x[0] = beta;
+        for k in 0..x.len() {
+            w[(i+1+k, i)] = x[k];
+            w[(i, i+1+k)] = if k == 0 { beta } else { 0.0 };
+        }
+    }
+
+    let d:Vec<f64> = (0..n).map(|i| w[(i, i)]).collect();
+    let e:Vec<f64> = (0..n.saturating_sub(1)).map(|i| w[(i+1, i)]).collect();
+
+    if !want_q {
+        return (d, e, None);
+    }
+
+    let mut q = Matrix::identity(n);
+    if n > 1 {
+        let q1 = form_q(w.view().subview(1..n, 0..n-1), &tau[0..n-2], n-1);
+        q.view_mut().subview_mut(1..n, 1..n).copy_from(q1.view());
+    }
+
+    return (d, e, Some(q));
+}
+
+// Rotates rows k and k+1 of zt: the eigenvectors are kept as rows so that every rotation
+// touches contiguous memory.
+fn rotate_rows(zt:&mut Matrix, k:usize, c:f64, s:f64) {
+    let m = zt.ncol;
+    let (top, bottom) = zt.as_mut_slice().split_at_mut((k+1)*m);
+    let rk = &mut top[k*m..(k+1)*m];
+    let rk1 = &mut bottom[0..m];
+
+    for j in 0..m {
+        let x = rk[j];
+        let y = rk1[j];
+        rk[j] = c*x+s*y;
+        rk1[j] = -s*x+c*y;
+    }
+}
+
+// One implicit QR step with Wilkinson shift on the unreduced block l..=h of the tridiagonal
+// (d, e), chasing the bulge down with Givens rotations.
+fn tridiagonal_qr_step(d:&mut [f64], e:&mut [f64], l:usize, h:usize, mut zt:Option<&mut Matrix>) {
+    let t = (d[h-1]-d[h])/2.0;
+    let b2 = e[h-1]*e[h-1];
+    let mu = d[h]-b2/(t+sgn(t)*t.hypot(e[h-1]));
+
+    let mut x = d[l]-mu;
+    let mut z = e[l];
+
+    for k in l..h {
+        let r = x.hypot(z);
+        let (c, s) = if r == 0.0 { (1.0, 0.0) } else { (x/r, z/r) };
+
+        if k > l {
+            e[k-1] = r;
+        }
+
+        let a = d[k];
+        let b = e[k];
+        let cc = d[k+1];
+
+        d[k] = c*c*a+2.0*c*s*b+s*s*cc;
+        d[k+1] = s*s*a-2.0*c*s*b+c*c*cc;
+        e[k] = c*s*(cc-a)+(c*c-s*s)*b;
+
+        if let Some(zt) = zt.as_deref_mut() {
+            rotate_rows(zt, k, c, s);
+        }
+
+        if k+1 < h {
+            x = e[k];
+            z = s*e[k+1];
+            e[k+1] *= c;
+        }
+    }
+}
+
+// Eigenvalues of the symmetric tridiagonal (d, e), and the rotations applied to zt if given.
+// Off-diagonals at or below tol*(|d_i| + |d_i+1|) are dropped; the run stops after max_iter
+// QR steps, 30 per eigenvalue by default, leaving the unreduced part of (d, e) in place.
+pub fn tridiagonal_eigen(d:&mut [f64], e:&mut [f64], mut zt:Option<&mut Matrix>, options:&IterOptions) -> Convergence {
+    let n = d.len();
+    let tol = options.tol_or(f64::EPSILON);
+    let max_iter = options.max_iter_or(SWEEPS_PER_EIGENVALUE*n);
+    let mut iter = 0;
+    let mut residual:f64 = 0.0;
+    let mut h = n.saturating_sub(1);
+
+    while h > 0 {
+        for i in 0..h {
+            if e[i] != 0.0 && e[i].abs() <= tol*(d[i].abs()+d[i+1].abs()) {
+                residual = residual.max(e[i].abs());
+                e[i] = 0.0;
+            }
+        }
+
+        if e[h-1] == 0.0 {
+            h -= 1;
+            continue;
+        }
+
+        if iter == max_iter {
+            let left = e[0..h].iter().fold(residual, |acc, x| acc.max(x.abs()));
+            return Convergence { iterations: iter, residual: left, converged: false };
+        }
+        iter += 1;
+
+        let mut l = h-1;
+        while l > 0 && e[l-1] != 0.0 {
+            l -= 1;
+        }
+
+        tridiagonal_qr_step(d, e, l, h, zt.as_deref_mut());
+    }
+
+    return Convergence { iterations: iter, residual, converged: true };
+}
+
+fn check_symmetric_input(a:&Matrix) -> Result<(), LinalgError> {
+    check_square(a.nrow, a.ncol)?;
+    check_finite(a.as_slice())?;
+    return Ok(());
+}
+
+// Eigenvalues of symmetric A in ascending order. Only the lower triangle of A is read.
+pub fn symmetric_eigenvalues(a:&Matrix) -> Result<Vec<f64>, LinalgError> {
+    let (w, conv) = symmetric_eigenvalues_with_options(a, &IterOptions::default())?;
+    conv.check()?;
+    return Ok(w);
+}
+
+// As symmetric_eigenvalues, reporting convergence instead of failing when max_iter is reached.
+pub fn symmetric_eigenvalues_with_options(a:&Matrix, options:&IterOptions) -> Result<(Vec<f64>, Convergence), LinalgError> {
+    check_symmetric_input(a)?;
+
+    let (mut d, mut e, _) = tridiagonalize(a, false);
+    let conv = tridiagonal_eigen(&mut d, &mut e, None, options);
+    d.sort_by(|p, q| p.total_cmp(q));
+
+    return Ok((d, conv));
+}
+
+// A = V diag(w) V' for symmetric A, with w ascending and the orthonormal eigenvectors as the
+// columns of V. Only the lower triangle of A is read.
+pub fn symmetric_eigen(a:&Matrix) -> Result<(Vec<f64>, Matrix), LinalgError> {
+    let (w, v, conv) = symmetric_eigen_with_options(a, &IterOptions::default())?;
+    conv.check()?;
+    return Ok((w, v));
+}
+
+// As symmetric_eigen, reporting convergence instead of failing when max_iter is reached.
+pub fn symmetric_eigen_with_options(a:&Matrix, options:&IterOptions) -> Result<(Vec<f64>, Matrix, Convergence), LinalgError> {
+    check_symmetric_input(a)?;
+
+    let n = a.nrow;
+    let (mut d, mut e, q) = tridiagonalize(a, true);
+    let mut zt = q.unwrap().transpose();
+    let conv = tridiagonal_eigen(&mut d, &mut e, Some(&mut zt), options);
+
+    let mut order:Vec<usize> = (0..n).collect();
+    order.sort_by(|&p, &q| d[p].total_cmp(&d[q]));
+
+    let w:Vec<f64> = order.iter().map(|&i| d[i]).collect();
+    let mut v = Matrix::zeros(n, n);
+    for (j, &k) in order.iter().enumerate() {
+        let row = zt.row(k);
+        for i in 0..n {
+            v[(i, j)] = row[i];
+        }
+    }
+
+    return Ok((w, v, conv));
+}
+
+// As symmetric_eigen_with_options, with the algorithm chosen by backend.
+pub fn symmetric_eigen_with_backend(a:&Matrix, backend:EigenBackend, options:&IterOptions) -> Result<(Vec<f64>, Matrix, Convergence), LinalgError> {
+    return match backend {
+        EigenBackend::TridiagonalQr => symmetric_eigen_with_options(a, options),
+        EigenBackend::Jacobi => jacobi_eigen(a, options),
+    };
+}
+
+// Ax = lambda Bx for symmetric A and symmetric positive definite B. With B = LL' this is the
+// standard problem for C = L^-1 A L^-T, whose eigenvectors y give x = L^-T y. Eigenvalues are
+// ascending and the eigenvectors, the columns of X, are B-orthonormal: X'BX = I. Only the lower
+// triangles of A and B are read; B that is not positive definite gives NotPositiveDefinite.
+pub fn generalized_eigh(a:&Matrix, b:&Matrix) -> Result<(Vec<f64>, Matrix), LinalgError> {
+    let (w, x, conv) = generalized_eigh_with_options(a, b, &IterOptions::default())?;
+    conv.check()?;
+    return Ok((w, x));
+}
+
+// As generalized_eigh, reporting convergence instead of failing when max_iter is reached.
+pub fn generalized_eigh_with_options(a:&Matrix, b:&Matrix, options:&IterOptions) -> Result<(Vec<f64>, Matrix, Convergence), LinalgError> {
+    check_symmetric_input(a)?;
+    check_symmetric_input(b)?;
+    if b.shape() != a.shape() {
+        return Err(LinalgError::DimensionMismatch { expected: a.shape(), found: b.shape() });
+    }
+
+    let n = a.nrow;
+    let l = cholesky(b)?;
+
+    // Row j of w is L^-1 a_j, so w = A L^-T and column j of C = L^-1 w is row j of C.
+    let mut w = Matrix::zeros(n, n);
+    for j in 0..n {
+        let mut aj = vec![0.0;n];
+        for i in 0..n {
+            aj[i] = if i >= j { a[(i, j)] } else { a[(j, i)] };
+        }
+        w.row_mut(j).copy_from_slice(&forward_sub(&l, &aj)?);
+    }
+
+    let mut c = Matrix::zeros(n, n);
+    for j in 0..n {
+        c.row_mut(j).copy_from_slice(&forward_sub(&l, &w.column(j))?);
+    }
+
+    let (vals, y, conv) = symmetric_eigen_with_options(&c, options)?;
+
+    let lt = l.transpose();
+    let mut x = Matrix::zeros(n, n);
+    for j in 0..n {
+        let xj = backward_sub(&lt, &y.column(j))?;
+        for i in 0..n {
+            x[(i, j)] = xj[i];
+        }
+    }
+
+    return Ok((vals, x, conv));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn random_symmetric(n:usize, seed:u64) -> Matrix {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut a = Matrix::zeros(n, n);
+        for i in 0..n {
+            for j in 0..i+1 {
+                let x = rng.gen_range(-1.0..1.0);
+                a[(i, j)] = x;
+                a[(j, i)] = x;
+            }
+        }
+        return a;
+    }
+
+    fn max_abs_diff(a:&Matrix, b:&Matrix) -> f64 {
+        return a.as_slice().iter().zip(b.as_slice().iter()).map(|(x, y)| (x-y).abs()).fold(0.0, f64::max);
+    }
+
+    #[test]
+    fn eigenpairs_have_small_residuals_and_orthonormal_vectors() {
+        for &n in &[1, 2, 7, 40] {
+            let a = random_symmetric(n, n as u64);
+            let (w, v) = symmetric_eigen(&a).unwrap();
+            assert!(w.windows(2).all(|p| p[0] <= p[1]));
+            assert!(max_abs_diff(&v.tr_mul(&v), &Matrix::identity(n)) < 1e-12);
+            let av = a.clone()*v.clone();
+            let vw = v.clone()*Matrix::from_diag(&w);
+            assert!(max_abs_diff(&av, &vw) < 1e-12*(n as f64), "n = {}", n);
+
+            let values = symmetric_eigenvalues(&a).unwrap();
+            for i in 0..n {
+                assert!((values[i]-w[i]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn tridiagonalization_reconstructs() {
+        let n = 9;
+        let a = random_symmetric(n, 11);
+        let (d, e, q) = tridiagonalize(&a, true);
+        let q = q.unwrap();
+        let mut t = Matrix::from_diag(&d);
+        for i in 0..n-1 {
+            t[(i+1, i)] = e[i];
+            t[(i, i+1)] = e[i];
+        }
+        assert!(max_abs_diff(&(q.clone()*t).mul_tr(&q), &a) < 1e-12);
+    }
+
+    #[test]
+    fn known_spectrum_and_repeated_eigenvalues() {
+        // Second-difference matrix: eigenvalues 2-2cos(k pi/(n+1)).
+        let n = 12;
+        let mut a = Matrix::zeros(n, n);
+        for i in 0..n {
+            a[(i, i)] = 2.0;
+            if i+1 < n {
+                a[(i, i+1)] = -1.0;
+                a[(i+1, i)] = -1.0;
+            }
+        }
+        let w = symmetric_eigenvalues(&a).unwrap();
+        for (k, wk) in w.iter().enumerate() {
+            let exact = 2.0-2.0*(((k+1) as f64)*std::f64::consts::PI/((n+1) as f64)).cos();
+            assert!((wk-exact).abs() < 1e-13);
+        }
+
+        let (w, v) = symmetric_eigen(&Matrix::identity(4)).unwrap();
+        assert_eq!(w, vec![1.0;4]);
+        assert!(max_abs_diff(&v.tr_mul(&v), &Matrix::identity(4)) < 1e-15);
+    }
+
+    #[test]
+    fn empty_and_invalid_input() {
+        assert_eq!(symmetric_eigenvalues(&Matrix::zeros(0, 0)).unwrap(), Vec::<f64>::new());
+        assert!(matches!(symmetric_eigen(&Matrix::zeros(2, 3)), Err(LinalgError::NotSquare { .. })));
+        assert!(matches!(symmetric_eigen(&Matrix::from_diag(&[1.0, f64::NAN])), Err(LinalgError::NonFinite)));
+    }
+
+    #[test]
+    fn generalized_eigh_is_b_orthonormal() {
+        let n = 20;
+        let a = random_symmetric(n, 11);
+        let c = random_symmetric(n, 12);
+        let b = &c.tr_mul(&c) + &Matrix::identity(n);
+
+        let (w, x) = generalized_eigh(&a, &b).unwrap();
+        assert!(w.windows(2).all(|p| p[0] <= p[1]));
+        assert!(max_abs_diff(&x.tr_mul(&(&b * &x)), &Matrix::identity(n)) < 1e-10);
+        let ax = &a * &x;
+        let bxw = &(&b * &x) * &Matrix::from_diag(&w);
+        assert!(max_abs_diff(&ax, &bxw) < 1e-10);
+    }
+
+    #[test]
+    fn generalized_eigh_with_diagonal_b_scales_the_problem() {
+        // With A = diag(a) and B = diag(b) the eigenvalues are a_i/b_i.
+        let a = Matrix::from_diag(&[2.0, 6.0, -3.0]);
+        let b = Matrix::from_diag(&[4.0, 2.0, 1.0]);
+        let (w, x) = generalized_eigh(&a, &b).unwrap();
+        for (wi, e) in w.iter().zip([-3.0, 0.5, 3.0].iter()) {
+            assert!((wi-e).abs() < 1e-14);
+        }
+        assert!((x[(0, 1)].abs()-0.5).abs() < 1e-14);
+    }
+
+    #[test]
+    fn generalized_eigh_rejects_indefinite_b() {
+        let a = Matrix::identity(2);
+        let b = Matrix::from_diag(&[1.0, -1.0]);
+        assert!(matches!(generalized_eigh(&a, &b), Err(LinalgError::NotPositiveDefinite { .. })));
+        assert!(matches!(generalized_eigh(&a, &Matrix::identity(3)), Err(LinalgError::DimensionMismatch { .. })));
+    }
+}